@@ -0,0 +1,682 @@
+//! Non-blocking versions of `Wikipedia`, `Page` and `Iter`, built on top of
+//! an `http::AsyncHttpClient`.
+//!
+//! Requires the `async` feature.
+use std::marker::PhantomData;
+use std::mem;
+use std::ops::{Deref, DerefMut};
+use std::pin::Pin;
+use std::vec::IntoIter;
+
+use futures::future::{self, BoxFuture, FutureExt};
+use futures::task::{Context, Poll};
+use futures::Stream;
+use serde_json::Value;
+
+use super::{ContQuery, DisambiguationOption, Document, Error, PageInfo, Result, Section, TitlePageId, Wikipedia, http, iter};
+use super::iter::Continuation;
+use super::{COORDINATES_PARAMS, CONTENT_PARAMS, HTML_CONTENT_PARAMS, INFO_PARAMS,
+    LANGUAGES_PARAMS, SUMMARY_PARAMS, USER_AGENT, WIKITEXT_PARAMS};
+use super::{disambiguation_error, disambiguation_params, first_page, geosearch_params,
+    is_disambiguation, owned_params, parse_cont, parse_cont_items, parse_coordinates,
+    parse_disambiguation_options, parse_extract, parse_info, parse_languages, parse_response, parse_results, parse_revision,
+    parse_section_list, parse_wikitext, parse_sections, random_params, redirect,
+    search_params, section_content, section_tree, sections_params, sort_disambiguation_options};
+
+type Batch = (Vec<Value>, Option<Vec<(String, String)>>);
+
+/// Like `Wikipedia`, but every request returns a future instead of blocking.
+///
+/// It dereferences to the `Wikipedia` holding its client and settings, such
+/// as `language` or `search_results`, so they work as in the blocking API.
+#[derive(Debug)]
+pub struct AsyncWikipedia<A: http::AsyncHttpClient> {
+    wikipedia: Wikipedia<A>,
+}
+
+impl<A: http::AsyncHttpClient> Default for AsyncWikipedia<A> {
+    fn default() -> Self {
+        AsyncWikipedia::new(A::default())
+    }
+}
+
+impl<A: http::AsyncHttpClient> Deref for AsyncWikipedia<A> {
+    type Target = Wikipedia<A>;
+
+    fn deref(&self) -> &Wikipedia<A> {
+        &self.wikipedia
+    }
+}
+
+impl<A: http::AsyncHttpClient> DerefMut for AsyncWikipedia<A> {
+    fn deref_mut(&mut self) -> &mut Wikipedia<A> {
+        &mut self.wikipedia
+    }
+}
+
+impl<A: http::AsyncHttpClient> AsyncWikipedia<A> {
+    /// Creates a new object using the provided client and default values.
+    pub fn new(mut client: A) -> Self {
+        client.user_agent(USER_AGENT.to_owned());
+        AsyncWikipedia { wikipedia: Wikipedia::with_client(client) }
+    }
+
+    /// Returns a list of languages in the form of (`identifier`, `language`),
+    /// for example [("en", "English"), ("es", "Español")]
+    pub fn get_languages(&self) -> BoxFuture<'_, Result<Vec<(String, String)>>> {
+        self.query(owned_params(LANGUAGES_PARAMS))
+            .map(|q| q.and_then(|q| parse_languages(&q)))
+            .boxed()
+    }
+
+    fn query(&self, mut args: Vec<(String, String)>) -> BoxFuture<'_, Result<Value>> {
+        args.extend(self.maxlag_param());
+        self.client.get(&self.base_url(), args)
+            .map(|response| parse_response(&try!(response)))
+            .boxed()
+    }
+
+    /// Searches for a string and returns a list of relevant page titles.
+    pub fn search(&self, query: &str) -> BoxFuture<'_, Result<Vec<String>>> {
+        self.query(search_params(self.search_results, query))
+            .map(|q| q.and_then(|q| parse_results(&q, "search")))
+            .boxed()
+    }
+
+    /// Search articles within `radius` meters of `latitude` and `longitude`.
+    pub fn geosearch(&self, latitude: f64, longitude: f64, radius: u16)
+            -> BoxFuture<'_, Result<Vec<String>>> {
+        let params = match geosearch_params(self.search_results, latitude, longitude, radius) {
+            Ok(p) => p,
+            Err(e) => return future::ready(Err(e)).boxed(),
+        };
+        self.query(params)
+            .map(|q| q.and_then(|q| parse_results(&q, "geosearch")))
+            .boxed()
+    }
+
+    /// Fetches `count` random articles' title.
+    pub fn random_count(&self, count: u8) -> BoxFuture<'_, Result<Vec<String>>> {
+        self.query(random_params(count))
+            .map(|q| q.and_then(|q| parse_results(&q, "random")))
+            .boxed()
+    }
+
+    /// Fetches a random article's title.
+    pub fn random(&self) -> BoxFuture<'_, Result<Option<String>>> {
+        self.random_count(1)
+            .map(|r| r.map(|r| r.into_iter().next()))
+            .boxed()
+    }
+
+    /// Creates a new `AsyncPage` given a `title`.
+    pub fn page_from_title<'a>(&'a self, title: String) -> AsyncPage<'a, A> {
+        AsyncPage::from_title(self, title)
+    }
+
+    /// Creates a new `AsyncPage` given a `pageid`.
+    pub fn page_from_pageid<'a>(&'a self, pageid: String) -> AsyncPage<'a, A> {
+        AsyncPage::from_pageid(self, pageid)
+    }
+}
+
+/// A wikipedia article, like `Page`, fetched without blocking.
+#[derive(Debug)]
+pub struct AsyncPage<'a, A: 'a + http::AsyncHttpClient> {
+    wikipedia: &'a AsyncWikipedia<A>,
+    identifier: TitlePageId,
+}
+
+impl<'a, A: http::AsyncHttpClient> Clone for AsyncPage<'a, A> {
+    fn clone(&self) -> Self {
+        AsyncPage { wikipedia: self.wikipedia, identifier: self.identifier.clone() }
+    }
+}
+
+impl<'a, A: http::AsyncHttpClient> AsyncPage<'a, A> {
+    /// Creates a new `AsyncPage` given a `title`.
+    pub fn from_title(wikipedia: &'a AsyncWikipedia<A>, title: String) -> AsyncPage<'a, A> {
+        AsyncPage { wikipedia, identifier: TitlePageId::Title(title) }
+    }
+
+    /// Creates a new `AsyncPage` given a `pageid`.
+    pub fn from_pageid(wikipedia: &'a AsyncWikipedia<A>, pageid: String) -> AsyncPage<'a, A> {
+        AsyncPage { wikipedia, identifier: TitlePageId::PageId(pageid) }
+    }
+
+    /// Runs a query on the page and parses it with `parse`, or calls `retry`
+    /// with the target page if the response is a redirect.
+    fn query<T, R, P>(&self, params: &[(&str, &str)], retry: R, parse: P)
+            -> BoxFuture<'a, Result<T>>
+            where T: Send + 'a,
+                  R: FnOnce(AsyncPage<'a, A>) -> BoxFuture<'a, Result<T>> + Send + 'a,
+                  P: FnOnce(&Value) -> Result<T> + Send + 'a {
+        self.query_then(params, retry, move |_, q| future::ready(parse(&q)).boxed())
+    }
+
+    /// Like `query`, but `parse` gets the `AsyncPage` too and returns a
+    /// future, so it can send further requests.
+    fn query_then<T, R, P>(&self, params: &[(&str, &str)], retry: R, parse: P)
+            -> BoxFuture<'a, Result<T>>
+            where T: Send + 'a,
+                  R: FnOnce(AsyncPage<'a, A>) -> BoxFuture<'a, Result<T>> + Send + 'a,
+                  P: FnOnce(AsyncPage<'a, A>, Value) -> BoxFuture<'a, Result<T>> + Send + 'a {
+        let page = self.clone();
+        let wikipedia = self.wikipedia;
+        wikipedia.query(self.identifier.with_params(params))
+            .then(move |q| match q {
+                Ok(q) => match redirect(&q) {
+                    Some(r) => retry(AsyncPage::from_title(wikipedia, r)),
+                    None => parse(page, q),
+                },
+                Err(e) => future::ready(Err(e)).boxed(),
+            })
+            .boxed()
+    }
+
+    /// Runs a query for an extract of the page. Like `Page::get_content`, it
+    /// fails with `Error::Disambiguation` on a disambiguation page if
+    /// `disambiguation_errors` is set.
+    fn query_extract<R>(&self, params: &[(&str, &str)], retry: R) -> BoxFuture<'a, Result<String>>
+            where R: FnOnce(AsyncPage<'a, A>) -> BoxFuture<'a, Result<String>> + Send + 'a {
+        if !self.wikipedia.disambiguation_errors {
+            return self.query(params, retry, parse_extract);
+        }
+        let params = disambiguation_params(params);
+        let params: Vec<_> = params.iter().map(|(k, v)| (&**k, &**v)).collect();
+        self.query_then(&params, retry, |page, q| {
+            if !first_page(&q).map(is_disambiguation).unwrap_or(false) {
+                return future::ready(parse_extract(&q)).boxed();
+            }
+            page.disambiguation_options()
+                .map(|options| options.and_then(|o| Err(disambiguation_error(o))))
+                .boxed()
+        })
+    }
+
+    /// Gets the `AsyncPage`'s metadata. Redirects are followed, the
+    /// response already has the page they lead to.
+    pub fn get_info(&self) -> BoxFuture<'a, Result<PageInfo>> {
+        self.wikipedia.query(self.identifier.with_params(INFO_PARAMS))
+            .map(|q| q.and_then(|q| parse_info(&q)))
+            .boxed()
+    }
+
+    /// Returns true if the `AsyncPage` is a disambiguation page.
+    pub fn is_disambiguation(&self) -> BoxFuture<'a, Result<bool>> {
+        self.get_info().map(|i| i.map(|i| i.disambiguation)).boxed()
+    }
+
+    /// Fetches the articles linked from the `AsyncPage`, sorted by title,
+    /// like `Page::disambiguation_options`.
+    pub fn disambiguation_options(&self) -> BoxFuture<'a, Result<Vec<DisambiguationOption>>> {
+        self.disambiguation_options_after(Vec::new(), None)
+    }
+
+    fn disambiguation_options_after(&self, mut options: Vec<DisambiguationOption>,
+            cont: Option<Vec<(String, String)>>)
+            -> BoxFuture<'a, Result<Vec<DisambiguationOption>>> {
+        let page = self.clone();
+        let params = self.wikipedia.disambiguation_options_query().params(&self.identifier, &cont);
+        self.wikipedia.query(params)
+            .then(move |q| {
+                let q = match q {
+                    Ok(q) => q,
+                    Err(e) => return future::ready(Err(e)).boxed(),
+                };
+                options.extend(parse_disambiguation_options(&q));
+                match parse_cont(&q) {
+                    Ok(Some(cont)) => page.disambiguation_options_after(options, Some(cont)),
+                    Ok(None) => future::ready(Ok(sort_disambiguation_options(options))).boxed(),
+                    Err(e) => future::ready(Err(e)).boxed(),
+                }
+            })
+            .boxed()
+    }
+
+    /// Gets the `AsyncPage`'s `pageid`.
+    pub fn get_pageid(&self) -> BoxFuture<'a, Result<String>> {
+        match self.identifier {
+            TitlePageId::PageId(ref s) => future::ready(Ok(s.clone())).boxed(),
+            TitlePageId::Title(_) => self.get_info().map(|i| i.map(|i| i.pageid)).boxed(),
+        }
+    }
+
+    /// Gets the `AsyncPage`'s `title`.
+    pub fn get_title(&self) -> BoxFuture<'a, Result<String>> {
+        match self.identifier {
+            TitlePageId::Title(ref s) => future::ready(Ok(s.clone())).boxed(),
+            TitlePageId::PageId(_) => self.get_info().map(|i| i.map(|i| i.title)).boxed(),
+        }
+    }
+
+    /// Gets the markdown content of the article.
+    pub fn get_content(&self) -> BoxFuture<'a, Result<String>> {
+        self.query_extract(CONTENT_PARAMS, |p| p.get_content())
+    }
+
+    /// Gets the html content of the article.
+    pub fn get_html_content(&self) -> BoxFuture<'a, Result<String>> {
+        self.query(HTML_CONTENT_PARAMS, |p| p.get_html_content(), parse_revision)
+    }
+
+    /// Gets the wikitext source of the article.
+    pub fn get_wikitext(&self) -> BoxFuture<'a, Result<String>> {
+        self.query(WIKITEXT_PARAMS, |p| p.get_wikitext(), parse_wikitext)
+    }
+
+    /// Gets a summary of the article.
+    pub fn get_summary(&self) -> BoxFuture<'a, Result<String>> {
+        self.query_extract(SUMMARY_PARAMS, |p| p.get_summary())
+    }
+
+    /// Returns the latitude and longitude associated to the `AsyncPage` if any.
+    pub fn get_coordinates(&self) -> BoxFuture<'a, Result<Option<(f64, f64)>>> {
+        self.query(COORDINATES_PARAMS, |p| p.get_coordinates(), parse_coordinates)
+    }
+
+    /// Fetches all sections of the article.
+    pub fn get_sections(&self) -> BoxFuture<'a, Result<Vec<String>>> {
+        let wikipedia = self.wikipedia;
+        self.get_pageid()
+            .then(move |pageid| match pageid {
+                Ok(pageid) => wikipedia.query(sections_params(&pageid))
+                    .map(|q| q.and_then(|q| parse_sections(&q)))
+                    .boxed(),
+                Err(e) => future::ready(Err(e)).boxed(),
+            })
+            .boxed()
+    }
+
+    /// Fetches all sections of the article, nested by level.
+    pub fn get_section_tree(&self) -> BoxFuture<'a, Result<Vec<Section>>> {
+        let wikipedia = self.wikipedia;
+        self.get_pageid()
+            .then(move |pageid| match pageid {
+                Ok(pageid) => wikipedia.query(sections_params(&pageid))
+                    .map(|q| q.and_then(|q| parse_section_list(&q)).map(section_tree))
+                    .boxed(),
+                Err(e) => future::ready(Err(e)).boxed(),
+            })
+            .boxed()
+    }
+
+    /// Fetches the content of the article split into sections and
+    /// paragraphs.
+    pub fn get_document(&self) -> BoxFuture<'a, Result<Document>> {
+        self.get_content()
+            .map(|c| c.map(|c| Document::parse(&c)))
+            .boxed()
+    }
+
+    /// Fetches the content of a section.
+    pub fn get_section_content(&self, title: &str) -> BoxFuture<'a, Result<Option<String>>> {
+        let title = title.to_owned();
+        self.get_content()
+            .map(move |content| content.map(|c| section_content(&c, &title)))
+            .boxed()
+    }
+
+    fn request_cont(&self, query: ContQuery, cont: &Option<Vec<(String, String)>>)
+            -> BoxFuture<'a, Result<Batch>> {
+        let field = query.field;
+        self.wikipedia.query(query.params(&self.identifier, cont))
+            .map(move |q| q.and_then(|q| parse_cont_items(&q, field)))
+            .boxed()
+    }
+
+    fn request_images(&self, cont: &Option<Vec<(String, String)>>)
+            -> BoxFuture<'a, Result<Batch>> {
+        self.request_cont(self.wikipedia.images_query(), cont)
+    }
+
+    /// Creates a stream to view all images in the `AsyncPage`.
+    pub fn get_images(&self) -> BoxFuture<'a, Result<AsyncIter<'a, A, iter::Image>>> {
+        AsyncIter::new(self.clone())
+    }
+
+    fn request_extlinks(&self, cont: &Option<Vec<(String, String)>>)
+            -> BoxFuture<'a, Result<Batch>> {
+        self.request_cont(self.wikipedia.extlinks_query(), cont)
+    }
+
+    /// Creates a stream to view all references (external links) in the `AsyncPage`.
+    pub fn get_references(&self) -> BoxFuture<'a, Result<AsyncIter<'a, A, iter::Reference>>> {
+        AsyncIter::new(self.clone())
+    }
+
+    fn request_links(&self, cont: &Option<Vec<(String, String)>>)
+            -> BoxFuture<'a, Result<Batch>> {
+        self.request_cont(self.wikipedia.links_query(), cont)
+    }
+
+    /// Creates a stream to view all internal links in the `AsyncPage`.
+    pub fn get_links(&self) -> BoxFuture<'a, Result<AsyncIter<'a, A, iter::Link>>> {
+        AsyncIter::new(self.clone())
+    }
+
+    fn request_categories(&self, cont: &Option<Vec<(String, String)>>)
+            -> BoxFuture<'a, Result<Batch>> {
+        self.request_cont(self.wikipedia.categories_query(), cont)
+    }
+
+    /// Creates a stream to view all categories of the `AsyncPage`.
+    pub fn get_categories(&self) -> BoxFuture<'a, Result<AsyncIter<'a, A, iter::Category>>> {
+        AsyncIter::new(self.clone())
+    }
+}
+
+/// Like `Page`, two `AsyncPage`s are equal when they have the same title or
+/// `pageid` on the same wiki, without sending requests.
+impl<'a, A: http::AsyncHttpClient> PartialEq<AsyncPage<'a, A>> for AsyncPage<'a, A> {
+    fn eq(&self, other: &AsyncPage<A>) -> bool {
+        self.identifier == other.identifier && self.wikipedia.base_url() == other.wikipedia.base_url()
+    }
+}
+
+/// A `Stream` over the items of an `AsyncPage`, following `continue`
+/// parameters as needed. It is the non-blocking counterpart of `Iter`.
+///
+/// Like `Iter`, it ends at the first error. Use `results` to get the errors.
+pub struct AsyncIter<'a, A: 'a + http::AsyncHttpClient, B: AsyncIterItem> {
+    page: AsyncPage<'a, A>,
+    inner: IntoIter<Value>,
+    cont: Option<Vec<(String, String)>>,
+    current: Option<Vec<(String, String)>>,
+    offset: usize,
+    pending: Option<BoxFuture<'a, Result<Batch>>>,
+    phantom: PhantomData<fn() -> B>,
+}
+
+impl<'a, A: http::AsyncHttpClient, B: AsyncIterItem + 'a> AsyncIter<'a, A, B> {
+    /// Fetches the first batch of items of `page`.
+    pub fn new(page: AsyncPage<'a, A>) -> BoxFuture<'a, Result<AsyncIter<'a, A, B>>> {
+        AsyncIter::resume(page, &Continuation::default())
+    }
+
+    /// Creates a stream that goes on from the position saved in
+    /// `continuation`, like `Iter::resume`.
+    pub fn resume(page: AsyncPage<'a, A>, continuation: &Continuation)
+            -> BoxFuture<'a, Result<AsyncIter<'a, A, B>>> {
+        let continuation = continuation.clone();
+        B::request_next_async(&page, &continuation.cont)
+            .map(move |r| r.map(|(array, cont)| {
+                let mut inner = array.into_iter();
+                for _ in 0..continuation.offset {
+                    inner.next();
+                }
+                AsyncIter {
+                    page,
+                    inner,
+                    cont,
+                    current: continuation.cont,
+                    offset: continuation.offset,
+                    pending: None,
+                    phantom: PhantomData,
+                }
+            }))
+            .boxed()
+    }
+}
+
+impl<'a, A: http::AsyncHttpClient, B: AsyncIterItem> AsyncIter<'a, A, B> {
+    /// Polls for the next item. Like `Iter::try_next`, a failed request is
+    /// reported and retried on the following poll, and an item that cannot
+    /// be parsed is reported as `Error::JSONPathError`.
+    fn poll_try_next(&mut self, cx: &mut Context) -> Poll<Result<Option<B>>> {
+        loop {
+            if let Some(ref mut pending) = self.pending {
+                match pending.as_mut().poll(cx) {
+                    Poll::Pending => return Poll::Pending,
+                    Poll::Ready(Ok((array, cont))) => {
+                        self.inner = array.into_iter();
+                        self.current = mem::replace(&mut self.cont, cont);
+                        self.offset = 0;
+                    },
+                    Poll::Ready(Err(e)) => {
+                        self.pending = None;
+                        return Poll::Ready(Err(e));
+                    },
+                }
+            }
+            self.pending = None;
+            match self.inner.next() {
+                Some(ref v) => {
+                    self.offset += 1;
+                    return Poll::Ready(B::from_value(v).map(Some).ok_or(Error::JSONPathError));
+                },
+                None => match self.cont {
+                    Some(_) => self.pending = Some(B::request_next_async(&self.page, &self.cont)),
+                    None => return Poll::Ready(Ok(None)),
+                },
+            }
+        }
+    }
+
+    /// Returns the current position, to be used with `resume`.
+    pub fn continuation(&self) -> Continuation {
+        Continuation { cont: self.current.clone(), offset: self.offset }
+    }
+
+    /// Converts the stream into one that yields `Result`s, see `Iter::results`.
+    pub fn results(self) -> AsyncResults<'a, A, B> {
+        AsyncResults { iter: self }
+    }
+}
+
+impl<'a, A: http::AsyncHttpClient, B: AsyncIterItem> Stream for AsyncIter<'a, A, B> {
+    type Item = B;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        self.get_mut().poll_try_next(cx).map(|r| r.unwrap_or(None))
+    }
+}
+
+/// A `Stream` over the items of an `AsyncIter` that reports errors.
+///
+/// After an error the next poll retries the failed request, so a persistent
+/// failure yields errors indefinitely.
+pub struct AsyncResults<'a, A: 'a + http::AsyncHttpClient, B: AsyncIterItem> {
+    iter: AsyncIter<'a, A, B>,
+}
+
+impl<'a, A: http::AsyncHttpClient, B: AsyncIterItem> AsyncResults<'a, A, B> {
+    /// Returns the underlying `AsyncIter`.
+    pub fn into_inner(self) -> AsyncIter<'a, A, B> {
+        self.iter
+    }
+}
+
+impl<'a, A: http::AsyncHttpClient, B: AsyncIterItem> Stream for AsyncResults<'a, A, B> {
+    type Item = Result<B>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        self.get_mut().iter.poll_try_next(cx).map(|r| match r {
+            Ok(Some(b)) => Some(Ok(b)),
+            Ok(None) => None,
+            Err(e) => Some(Err(e)),
+        })
+    }
+}
+
+/// An `IterItem` that can also be fetched by an `AsyncIter`.
+pub trait AsyncIterItem: iter::IterItem {
+    fn request_next_async<'a, A: http::AsyncHttpClient>(page: &AsyncPage<'a, A>,
+            cont: &Option<Vec<(String, String)>>) -> BoxFuture<'a, Result<Batch>>;
+}
+
+impl AsyncIterItem for iter::Image {
+    fn request_next_async<'a, A: http::AsyncHttpClient>(page: &AsyncPage<'a, A>,
+            cont: &Option<Vec<(String, String)>>) -> BoxFuture<'a, Result<Batch>> {
+        page.request_images(cont)
+    }
+}
+
+impl AsyncIterItem for iter::Reference {
+    fn request_next_async<'a, A: http::AsyncHttpClient>(page: &AsyncPage<'a, A>,
+            cont: &Option<Vec<(String, String)>>) -> BoxFuture<'a, Result<Batch>> {
+        page.request_extlinks(cont)
+    }
+}
+
+impl AsyncIterItem for iter::Link {
+    fn request_next_async<'a, A: http::AsyncHttpClient>(page: &AsyncPage<'a, A>,
+            cont: &Option<Vec<(String, String)>>) -> BoxFuture<'a, Result<Batch>> {
+        page.request_links(cont)
+    }
+}
+
+impl AsyncIterItem for iter::Category {
+    fn request_next_async<'a, A: http::AsyncHttpClient>(page: &AsyncPage<'a, A>,
+            cont: &Option<Vec<(String, String)>>) -> BoxFuture<'a, Result<Batch>> {
+        page.request_categories(cont)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::AsyncWikipedia;
+    use super::super::Error;
+    use super::super::http::mock::MockClient;
+    use super::super::iter;
+    use futures::executor::block_on;
+    use futures::StreamExt;
+
+    #[test]
+    fn search() {
+        let wikipedia = AsyncWikipedia::<MockClient>::default();
+        wikipedia.client.respond("{\"query\":{\"search\":[{\"title\":\"hello\"}, {\"title\":\"world\"}]}}");
+        assert_eq!(
+                block_on(wikipedia.search("hello world")).unwrap(),
+                vec![
+                "hello".to_owned(),
+                "world".to_owned(),
+                ]);
+        let requests = wikipedia.client.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].base_url, "https://en.wikipedia.org/w/api.php".to_owned());
+        assert_eq!(requests[0].args,
+                vec![
+                    ("list".to_owned(), "search".to_owned()),
+                    ("srprop".to_owned(), "".to_owned()),
+                    ("srlimit".to_owned(), "10".to_owned()),
+                    ("srsearch".to_owned(), "hello world".to_owned()),
+                    ("format".to_owned(), "json".to_owned()),
+                    ("action".to_owned(), "query".to_owned())
+                    ]);
+    }
+
+    #[test]
+    fn geosearch_invalid_parameter() {
+        let wikipedia = AsyncWikipedia::<MockClient>::default();
+        block_on(wikipedia.geosearch(91.0, 0.0, 10)).unwrap_err();
+        assert!(wikipedia.client.requests().is_empty());
+    }
+
+    #[test]
+    fn page_redirect_summary() {
+        let wikipedia = AsyncWikipedia::<MockClient>::default();
+        wikipedia.client.respond("{\"query\":{\"redirects\":[{\"to\":\"hello world\"}]}}");
+        wikipedia.client.respond("{\"query\":{\"pages\":{\"a\":{\"extract\":\"hello\"}}}}");
+        let page = wikipedia.page_from_title("Parkinson's law of triviality".to_owned());
+        assert_eq!(block_on(page.get_summary()).unwrap(), "hello");
+        let requests = wikipedia.client.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[1].arg("titles"), Some("hello world"));
+    }
+
+    #[test]
+    fn disambiguation() {
+        let mut wikipedia = AsyncWikipedia::<MockClient>::default();
+        wikipedia.client.respond("{\"query\":{\"pages\":{\"19694\":{\"pageid\":19694,\"title\":\"Mercury\",\"extract\":\"Mercury may refer to:\",\"pageprops\":{\"disambiguation\":\"\"}}}}}");
+        wikipedia.client.respond("{\"continue\":{\"gplcontinue\":\"19694|0|Mercury_(planet)\",\"continue\":\"gplcontinue||\"},\"query\":{\"pages\":{\"19007\":{\"pageid\":19007,\"title\":\"Mercury (element)\"}}}}");
+        wikipedia.client.respond("{\"query\":{\"pages\":{\"19001\":{\"pageid\":19001,\"title\":\"Mercury (planet)\"}}}}");
+        wikipedia.disambiguation_errors = true;
+        let page = wikipedia.page_from_title("Mercury".to_owned());
+        match block_on(page.get_summary()) {
+            Err(Error::Disambiguation(titles)) => assert_eq!(titles, vec![
+                "Mercury (element)".to_owned(),
+                "Mercury (planet)".to_owned(),
+            ]),
+            r => panic!("unexpected {:?}", r),
+        }
+        let requests = wikipedia.client.requests();
+        assert_eq!(requests.len(), 3);
+        assert_eq!(requests[0].arg("ppprop"), Some("disambiguation"));
+        assert_eq!(requests[2].arg("gplcontinue"), Some("19694|0|Mercury_(planet)"));
+    }
+
+    #[test]
+    fn page_sections() {
+        let wikipedia = AsyncWikipedia::<MockClient>::default();
+        wikipedia.client.respond("{\"query\":{\"pages\":{\"123\":{}}}}");
+        wikipedia.client.respond("{\"parse\":{\"sections\":[{\"line\":\"hello\"}, {\"line\":\"world\"}]}}");
+        let page = wikipedia.page_from_title("World".to_owned());
+        assert_eq!(
+                block_on(page.get_sections()).unwrap(),
+                vec!["hello".to_owned(), "world".to_owned()]
+                );
+        assert_eq!(wikipedia.client.requests()[1].args,
+                vec![
+                    ("prop".to_owned(), "sections".to_owned()),
+                    ("format".to_owned(), "json".to_owned()),
+                    ("action".to_owned(), "parse".to_owned()),
+                    ("pageid".to_owned(), "123".to_owned())
+                    ]);
+    }
+
+    #[test]
+    fn get_links() {
+        let wikipedia = AsyncWikipedia::<MockClient>::default();
+        wikipedia.client.respond("{\"continue\": {\"lol\":\"1\"},\"query\":{\"pages\":{\"a\":{\"links\":[{\"title\": \"Hello\"}]}}}}");
+        wikipedia.client.respond("{\"query\":{\"pages\":{\"a\":{\"links\":[{\"title\": \"World\"}]}}}}");
+        let page = wikipedia.page_from_title("World".to_owned());
+        let links = block_on(page.get_links()).unwrap();
+        assert_eq!(
+                block_on(links.collect::<Vec<_>>()),
+                vec![
+                iter::Link {
+                    title: "Hello".to_owned(),
+                },
+                iter::Link {
+                    title: "World".to_owned(),
+                }
+                ]);
+        assert_eq!(wikipedia.client.requests().into_iter().map(|r| r.args).collect::<Vec<_>>(),
+                vec![
+                vec![
+                    ("prop".to_owned(), "links".to_owned()),
+                    ("plnamespace".to_owned(), "0".to_owned()),
+                    ("ellimit".to_owned(), "max".to_owned()),
+                    ("format".to_owned(), "json".to_owned()),
+                    ("action".to_owned(), "query".to_owned()),
+                    ("titles".to_owned(), "World".to_owned()),
+                    ("continue".to_owned(), "".to_owned()),
+                ],
+                vec![
+                    ("prop".to_owned(), "links".to_owned()),
+                    ("plnamespace".to_owned(), "0".to_owned()),
+                    ("ellimit".to_owned(), "max".to_owned()),
+                    ("format".to_owned(), "json".to_owned()),
+                    ("action".to_owned(), "query".to_owned()),
+                    ("titles".to_owned(), "World".to_owned()),
+                    ("lol".to_owned(), "1".to_owned()),
+                ]
+                ]);
+    }
+
+    #[test]
+    fn get_links_retry() {
+        let wikipedia = AsyncWikipedia::<MockClient>::default();
+        wikipedia.client.respond("{\"continue\": {\"lol\":\"1\"},\"query\":{\"pages\":{\"a\":{\"links\":[{\"title\": \"Hello\"}]}}}}");
+        wikipedia.client.respond("{\"query\":");
+        wikipedia.client.respond("{\"query\":{\"pages\":{\"a\":{\"links\":[{\"title\": \"World\"}]}}}}");
+        let page = wikipedia.page_from_title("World".to_owned());
+        let results = block_on(page.get_links()).unwrap().results();
+        let links = block_on(results.collect::<Vec<_>>());
+        assert_eq!(links.len(), 3);
+        assert_eq!(*links[0].as_ref().unwrap(), iter::Link { title: "Hello".to_owned() });
+        assert!(links[1].is_err());
+        assert_eq!(*links[2].as_ref().unwrap(), iter::Link { title: "World".to_owned() });
+    }
+}
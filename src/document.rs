@@ -0,0 +1,164 @@
+//! A plain text article split into sections and paragraphs, see
+//! `Page::get_document`.
+use std::vec::IntoIter;
+
+use super::headings;
+
+/// An article parsed from its plain text content.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Document {
+    /// Paragraphs before the first heading.
+    pub paragraphs: Vec<String>,
+    /// Top level sections.
+    pub sections: Vec<DocumentSection>,
+}
+
+/// A section of a `Document`, with the sections nested under it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DocumentSection {
+    pub title: String,
+    /// Heading level, 2 for `== x ==`, 3 for `=== x ===` and so on.
+    pub level: u8,
+    /// Paragraphs before the first nested heading.
+    pub paragraphs: Vec<String>,
+    pub sections: Vec<DocumentSection>,
+}
+
+/// A paragraph of a `Document` with the titles of the sections it is in.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Paragraph<'a> {
+    /// Titles from the top level section to the innermost one, empty for the
+    /// paragraphs before the first heading.
+    pub path: Vec<&'a str>,
+    pub text: &'a str,
+}
+
+impl Document {
+    /// Parses the plain text returned by `Page::get_content`. Headings are
+    /// lines like `== title ==` and every other non-empty line is a
+    /// paragraph.
+    pub fn parse(text: &str) -> Document {
+        let headings = headings(text);
+        let lead_end = headings.first().map(|h| h.start).unwrap_or(text.len());
+        let mut document = Document {
+            paragraphs: split_paragraphs(&text[..lead_end]),
+            sections: Vec::new(),
+        };
+        for (i, heading) in headings.iter().enumerate() {
+            let end = headings.get(i + 1).map(|h| h.start).unwrap_or(text.len());
+            add_section(&mut document.sections, DocumentSection {
+                title: heading.title.clone(),
+                level: heading.level,
+                paragraphs: split_paragraphs(&text[heading.end..end]),
+                sections: Vec::new(),
+            });
+        }
+        document
+    }
+
+    /// Returns every paragraph in document order.
+    pub fn paragraphs(&self) -> IntoIter<Paragraph<'_>> {
+        let mut paragraphs: Vec<Paragraph> = self.paragraphs
+            .iter()
+            .map(|p| Paragraph { path: Vec::new(), text: p })
+            .collect();
+        for section in &self.sections {
+            section.collect_paragraphs(&mut Vec::new(), &mut paragraphs);
+        }
+        paragraphs.into_iter()
+    }
+
+    /// Finds a section by the titles leading to it, for example
+    /// `&["History", "Ancient"]`.
+    pub fn section(&self, path: &[&str]) -> Option<&DocumentSection> {
+        let (first, rest) = match path.split_first() {
+            Some(x) => x,
+            None => return None,
+        };
+        self.sections
+            .iter()
+            .find(|s| s.title == *first)
+            .and_then(|s| s.section(rest))
+    }
+}
+
+impl DocumentSection {
+    /// Returns the section itself for an empty `path`, otherwise finds a
+    /// nested section like `Document::section`.
+    pub fn section(&self, path: &[&str]) -> Option<&DocumentSection> {
+        let (first, rest) = match path.split_first() {
+            Some(p) => p,
+            None => return Some(self),
+        };
+        self.sections
+            .iter()
+            .find(|s| s.title == *first)
+            .and_then(|s| s.section(rest))
+    }
+
+    /// Joins the paragraphs of the section and all nested sections.
+    pub fn text(&self) -> String {
+        let mut paragraphs = Vec::new();
+        self.collect_paragraphs(&mut Vec::new(), &mut paragraphs);
+        paragraphs.iter().map(|p| p.text).collect::<Vec<_>>().join("\n")
+    }
+
+    fn collect_paragraphs<'a>(&'a self, path: &mut Vec<&'a str>, out: &mut Vec<Paragraph<'a>>) {
+        path.push(&self.title);
+        for p in &self.paragraphs {
+            out.push(Paragraph { path: path.clone(), text: p });
+        }
+        for section in &self.sections {
+            section.collect_paragraphs(path, out);
+        }
+        path.pop();
+    }
+}
+
+fn split_paragraphs(text: &str) -> Vec<String> {
+    text.lines()
+        .map(|l| l.trim())
+        .filter(|l| !l.is_empty())
+        .map(|l| l.to_owned())
+        .collect()
+}
+
+/// Nests `section` under the last of `siblings` if it has a higher level.
+fn add_section(siblings: &mut Vec<DocumentSection>, section: DocumentSection) {
+    match siblings.last_mut() {
+        Some(last) if last.level < section.level => return add_section(&mut last.sections, section),
+        _ => (),
+    }
+    siblings.push(section);
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Document, Paragraph};
+
+    const CONTENT: &str = "Intro.\nMore intro.\n\n\n== History ==\nOld.\n\n\n=== Rome ===\nRoman.\n\n\n== Empty ==\n\n\n== Notes ==\nLast.\n";
+
+    #[test]
+    fn parse() {
+        let document = Document::parse(CONTENT);
+        assert_eq!(document.paragraphs, vec!["Intro.".to_owned(), "More intro.".to_owned()]);
+        assert_eq!(document.sections.len(), 3);
+        assert_eq!(document.sections[0].sections[0].title, "Rome".to_owned());
+        assert_eq!(document.sections[0].sections[0].level, 3);
+        assert!(document.sections[1].paragraphs.is_empty());
+        assert_eq!(document.section(&["History", "Rome"]).unwrap().paragraphs,
+                vec!["Roman.".to_owned()]);
+        assert_eq!(document.section(&["History"]).unwrap().text(), "Old.\nRoman.".to_owned());
+        assert_eq!(document.section(&["Rome"]), None);
+    }
+
+    #[test]
+    fn paragraphs() {
+        let document = Document::parse(CONTENT);
+        let paragraphs: Vec<Paragraph> = document.paragraphs().collect();
+        assert_eq!(paragraphs.len(), 5);
+        assert_eq!(paragraphs[0], Paragraph { path: vec![], text: "Intro." });
+        assert_eq!(paragraphs[3], Paragraph { path: vec!["History", "Rome"], text: "Roman." });
+        assert_eq!(paragraphs[4], Paragraph { path: vec!["Notes"], text: "Last." });
+    }
+}
@@ -0,0 +1,596 @@
+//! Cleaning of the html returned by `Page::get_html_content`, and its
+//! conversion to Markdown or plain text.
+//!
+//! # Examples
+//!
+//! ```
+//! use wikipedia::html::Html;
+//!
+//! let html = "<p><b>Paris</b> is the capital of <a href=\"/wiki/France\">France</a>.\
+//!     <sup class=\"reference\">[1]</sup></p>";
+//! let markdown = Html::parse(html)
+//!     .clean()
+//!     .resolve_links("https://en.wikipedia.org/w/api.php")
+//!     .to_markdown();
+//! assert_eq!(markdown, "**Paris** is the capital of [France](https://en.wikipedia.org/wiki/France).");
+//! ```
+use super::wikitext::parse_attributes;
+
+/// Classes of the elements removed by `Html::clean`.
+const CLUTTER_CLASSES: &[&str] = &[
+    "mw-editsection",
+    "navbox",
+    "vertical-navbox",
+    "navbox-styles",
+    "reference",
+    "references",
+    "reflist",
+    "mw-references-wrap",
+    "mw-cite-backlink",
+    "noprint",
+    "metadata",
+    "mw-empty-elt",
+    "mw-jump-link",
+    "toc",
+];
+
+/// Elements without content or closing tag.
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source",
+    "track", "wbr",
+];
+
+/// A parsed html fragment.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Html {
+    pub nodes: Vec<HtmlNode>,
+}
+
+/// A piece of an `Html` fragment.
+#[derive(Debug, Clone, PartialEq)]
+pub enum HtmlNode {
+    /// Text, with entities decoded.
+    Text(String),
+    Element(Element),
+}
+
+/// An html element, such as `<p>...</p>`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Element {
+    /// Lowercase tag name.
+    pub name: String,
+    /// Attributes with lowercase names and decoded values.
+    pub attributes: Vec<(String, String)>,
+    pub children: Vec<HtmlNode>,
+}
+
+impl Element {
+    /// Returns the value of the attribute `name`.
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes.iter().find(|a| a.0 == name).map(|a| &*a.1)
+    }
+
+    /// Returns true if `class` is one of the element's classes.
+    pub fn has_class(&self, class: &str) -> bool {
+        self.attribute("class")
+            .map(|c| c.split_whitespace().any(|c| c == class))
+            .unwrap_or(false)
+    }
+
+    fn is_clutter(&self) -> bool {
+        let hidden = self.attribute("hidden").is_some() || self.attribute("style")
+            .map(|s| s.replace(char::is_whitespace, "").to_lowercase().contains("display:none"))
+            .unwrap_or(false);
+        hidden || ["script", "style", "link", "meta", "noscript"].contains(&&*self.name) ||
+            CLUTTER_CLASSES.iter().any(|c| self.has_class(c))
+    }
+}
+
+impl Html {
+    /// Parses an html fragment. Parsing never fails: unclosed elements are
+    /// closed at the end of their parent and stray closing tags are ignored.
+    pub fn parse(html: &str) -> Html {
+        let mut stack = vec![Element { name: String::new(), attributes: Vec::new(), children: Vec::new() }];
+        let mut rest = html;
+        while !rest.is_empty() {
+            if rest.starts_with("<!--") {
+                rest = rest.find("-->").map(|i| &rest[i + 3..]).unwrap_or("");
+            } else if rest.starts_with("<!") || rest.starts_with("<?") {
+                rest = rest.find('>').map(|i| &rest[i + 1..]).unwrap_or("");
+            } else if rest.starts_with("</") {
+                let end = rest.find('>').unwrap_or(rest.len());
+                close(&mut stack, &tag_name(&rest[2..end]));
+                rest = rest.get(end + 1..).unwrap_or("");
+            } else if rest.starts_with('<') && rest[1..].starts_with(|c: char| c.is_ascii_alphabetic()) {
+                let end = tag_end(rest);
+                let tag = &rest[1..end];
+                rest = rest.get(end + 1..).unwrap_or("");
+                let name = tag_name(tag);
+                let self_closing = tag.ends_with('/');
+                let element = Element {
+                    attributes: parse_attributes(tag[name.len()..].trim_end_matches('/'))
+                        .into_iter()
+                        .map(|(k, v)| (k, decode_entities(&v)))
+                        .collect(),
+                    name,
+                    children: Vec::new(),
+                };
+                if element.name == "script" || element.name == "style" {
+                    let close_tag = format!("</{}", element.name);
+                    rest = rest.to_ascii_lowercase().find(&*close_tag)
+                        .and_then(|i| rest[i..].find('>').map(|j| &rest[i + j + 1..]))
+                        .unwrap_or("");
+                    continue;
+                }
+                implied_end(&mut stack, &element.name);
+                if self_closing || VOID_ELEMENTS.contains(&&*element.name) {
+                    stack.last_mut().unwrap().children.push(HtmlNode::Element(element));
+                } else {
+                    stack.push(element);
+                }
+            } else {
+                let first = rest.chars().next().map(|c| c.len_utf8()).unwrap_or(1);
+                let end = rest[first..].find('<').map(|i| i + first).unwrap_or(rest.len());
+                let text = decode_entities(&rest[..end]);
+                rest = &rest[end..];
+                let children = &mut stack.last_mut().unwrap().children;
+                if let Some(&mut HtmlNode::Text(ref mut t)) = children.last_mut() {
+                    t.push_str(&text);
+                    continue;
+                }
+                children.push(HtmlNode::Text(text));
+            }
+        }
+        while stack.len() > 1 {
+            pop(&mut stack);
+        }
+        Html { nodes: stack.pop().unwrap().children }
+    }
+
+    /// Removes edit links, navigation boxes, references, the table of
+    /// contents, hidden elements, scripts and styles.
+    pub fn clean(mut self) -> Html {
+        clean(&mut self.nodes);
+        self
+    }
+
+    /// Makes the links relative to the wiki absolute, using the scheme and
+    /// host of `base_url`, for example `Wikipedia::base_url()`.
+    pub fn resolve_links(mut self, base_url: &str) -> Html {
+        let origin = match base_url.find("://") {
+            Some(i) => &base_url[..base_url[i + 3..].find('/').map(|j| i + 3 + j).unwrap_or(base_url.len())],
+            None => base_url,
+        };
+        let scheme = base_url.find("://").map(|i| &base_url[..i]).unwrap_or("https");
+        resolve_links(&mut self.nodes, origin, scheme);
+        self
+    }
+
+    /// Renders the fragment as Markdown, keeping headings, emphasis, links,
+    /// lists and tables.
+    pub fn to_markdown(&self) -> String {
+        Renderer { markdown: true, list_depth: 0, pre: false }.render(&self.nodes)
+    }
+
+    /// Renders the fragment as plain text. Lists keep their markers and
+    /// table cells are separated by tabs.
+    pub fn to_plain_text(&self) -> String {
+        Renderer { markdown: false, list_depth: 0, pre: false }.render(&self.nodes)
+    }
+}
+
+/// Finds the `>` that ends the tag at the start of `html`, skipping quoted
+/// attribute values.
+fn tag_end(html: &str) -> usize {
+    let mut quote = None;
+    for (i, c) in html.char_indices() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => (),
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if c == '>' => return i,
+            None => (),
+        }
+    }
+    html.len()
+}
+
+fn tag_name(tag: &str) -> String {
+    tag.trim_start()
+        .chars()
+        .take_while(|c| c.is_ascii_alphanumeric())
+        .collect::<String>()
+        .to_lowercase()
+}
+
+/// Closes the innermost open element called `name` and everything inside it.
+fn close(stack: &mut Vec<Element>, name: &str) {
+    if let Some(i) = stack.iter().rposition(|e| e.name == name) {
+        if i > 0 {
+            while stack.len() > i {
+                pop(stack);
+            }
+        }
+    }
+}
+
+/// Closes the elements that cannot contain an element called `name`, like a
+/// `<li>` followed by another `<li>`.
+fn implied_end(stack: &mut Vec<Element>, name: &str) {
+    let closes: &[&str] = match name {
+        "li" => &["li"],
+        "p" | "ul" | "ol" | "table" | "div" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => &["p"],
+        "td" | "th" => &["td", "th"],
+        "tr" => &["td", "th", "tr"],
+        "dt" | "dd" => &["dt", "dd"],
+        _ => &[],
+    };
+    while stack.len() > 1 && closes.contains(&&*stack[stack.len() - 1].name) {
+        pop(stack);
+    }
+}
+
+fn pop(stack: &mut Vec<Element>) {
+    let element = stack.pop().unwrap();
+    stack.last_mut().unwrap().children.push(HtmlNode::Element(element));
+}
+
+/// Decodes named entities common in Wikipedia's html and numeric ones.
+fn decode_entities(text: &str) -> String {
+    let mut out = String::new();
+    let mut rest = text;
+    while let Some(i) = rest.find('&') {
+        out.push_str(&rest[..i]);
+        rest = &rest[i..];
+        let end = match rest.find(';') {
+            Some(end) if end <= 10 => end,
+            _ => {
+                out.push('&');
+                rest = &rest[1..];
+                continue;
+            },
+        };
+        let entity = &rest[1..end];
+        let decoded = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some('\u{a0}'),
+            "ndash" => Some('\u{2013}'),
+            "mdash" => Some('\u{2014}'),
+            _ if entity.starts_with("#x") || entity.starts_with("#X") =>
+                u32::from_str_radix(&entity[2..], 16).ok().and_then(::std::char::from_u32),
+            _ if entity.starts_with('#') =>
+                entity[1..].parse::<u32>().ok().and_then(::std::char::from_u32),
+            _ => None,
+        };
+        match decoded {
+            Some(c) => {
+                out.push(c);
+                rest = &rest[end + 1..];
+            },
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            },
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn clean(nodes: &mut Vec<HtmlNode>) {
+    nodes.retain(|n| match *n {
+        HtmlNode::Element(ref e) => !e.is_clutter(),
+        HtmlNode::Text(_) => true,
+    });
+    for node in nodes.iter_mut() {
+        if let HtmlNode::Element(ref mut e) = *node {
+            clean(&mut e.children);
+        }
+    }
+}
+
+fn resolve_links(nodes: &mut [HtmlNode], origin: &str, scheme: &str) {
+    for node in nodes.iter_mut() {
+        if let HtmlNode::Element(ref mut e) = *node {
+            for attribute in e.attributes.iter_mut() {
+                if attribute.0 != "href" && attribute.0 != "src" {
+                    continue;
+                }
+                if attribute.1.starts_with("//") {
+                    attribute.1 = format!("{}:{}", scheme, attribute.1);
+                } else if attribute.1.starts_with('/') {
+                    attribute.1 = format!("{}{}", origin, attribute.1);
+                }
+            }
+            resolve_links(&mut e.children, origin, scheme);
+        }
+    }
+}
+
+struct Renderer {
+    markdown: bool,
+    list_depth: usize,
+    /// Inside a `<pre>`, where whitespace is kept.
+    pre: bool,
+}
+
+impl Renderer {
+    fn render(&mut self, nodes: &[HtmlNode]) -> String {
+        let text = self.nodes(nodes);
+        let mut out = String::new();
+        let mut blank_lines = 0;
+        for line in text.trim().lines() {
+            let line = line.trim_end();
+            if line.trim().is_empty() {
+                blank_lines += 1;
+                continue;
+            }
+            if !out.is_empty() {
+                out.push_str(if blank_lines > 0 { "\n\n" } else { "\n" });
+            }
+            out.push_str(line);
+            blank_lines = 0;
+        }
+        out
+    }
+
+    fn nodes(&mut self, nodes: &[HtmlNode]) -> String {
+        nodes.iter().map(|n| self.node(n)).collect()
+    }
+
+    fn node(&mut self, node: &HtmlNode) -> String {
+        let e = match *node {
+            HtmlNode::Text(ref t) if self.pre => return t.clone(),
+            HtmlNode::Text(ref t) => return collapse_whitespace(t),
+            HtmlNode::Element(ref e) => e,
+        };
+        match &*e.name {
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                let level = e.name[1..].parse::<usize>().unwrap_or(2);
+                let prefix = if self.markdown { format!("{} ", "#".repeat(level)) } else { String::new() };
+                format!("\n\n{}{}\n\n", prefix, self.inline(&e.children))
+            },
+            "p" | "div" | "section" | "figure" | "figcaption" | "center" | "dl" | "dt" | "dd" =>
+                format!("\n\n{}\n\n", self.nodes(&e.children).trim()),
+            "br" => "\n".to_owned(),
+            "hr" => if self.markdown { "\n\n---\n\n".to_owned() } else { "\n\n".to_owned() },
+            "b" | "strong" => self.emphasis(e, "**"),
+            "i" | "em" => self.emphasis(e, "*"),
+            "code" if self.markdown && !self.pre => {
+                let text = self.nodes(&e.children);
+                if text.trim().is_empty() { text } else { format!("`{}`", text) }
+            },
+            "a" => {
+                let text = self.nodes(&e.children);
+                match e.attribute("href") {
+                    Some(href) if self.markdown && !text.trim().is_empty() =>
+                        format!("[{}]({})", text.trim(), href.replace(' ', "%20")),
+                    _ => text,
+                }
+            },
+            "ul" => self.list(e, false),
+            "ol" => self.list(e, true),
+            "table" => self.table(e),
+            "pre" => {
+                self.pre = true;
+                let text = self.nodes(&e.children);
+                self.pre = false;
+                if self.markdown {
+                    format!("\n\n```\n{}\n```\n\n", text.trim_end())
+                } else {
+                    format!("\n\n{}\n\n", text.trim_end())
+                }
+            },
+            "blockquote" => {
+                let text = self.nodes(&e.children);
+                let prefix = if self.markdown { "> " } else { "" };
+                format!("\n\n{}\n\n", text
+                    .trim()
+                    .lines()
+                    .map(|l| format!("{}{}", prefix, l.trim()))
+                    .collect::<Vec<_>>()
+                    .join("\n"))
+            },
+            "img" => String::new(),
+            _ => self.nodes(&e.children),
+        }
+    }
+
+    /// Renders `nodes` in a single line.
+    fn inline(&mut self, nodes: &[HtmlNode]) -> String {
+        collapse_whitespace(&self.nodes(nodes)).trim().to_owned()
+    }
+
+    fn emphasis(&mut self, e: &Element, marker: &str) -> String {
+        let text = self.nodes(&e.children);
+        if !self.markdown || text.trim().is_empty() {
+            return text;
+        }
+        let start = &text[..text.len() - text.trim_start().len()];
+        let end = &text[text.trim_end().len()..];
+        format!("{}{}{}{}{}", start, marker, text.trim(), marker, end)
+    }
+
+    fn list(&mut self, e: &Element, ordered: bool) -> String {
+        let indent = "  ".repeat(self.list_depth);
+        self.list_depth += 1;
+        let mut out = String::from("\n");
+        let mut position = 0;
+        for child in &e.children {
+            let li = match *child {
+                HtmlNode::Element(ref li) if li.name == "li" => li,
+                _ => continue,
+            };
+            position += 1;
+            let marker = if ordered { format!("{}.", position) } else { "-".to_owned() };
+            let content = self.nodes(&li.children);
+            let mut lines = content.trim().lines().filter(|l| !l.trim().is_empty());
+            out.push_str(&format!("{}{} {}\n", indent, marker, lines.next().unwrap_or("").trim()));
+            for line in lines {
+                // Nested lists are already indented.
+                if line.starts_with(' ') {
+                    out.push_str(&format!("{}\n", line));
+                } else {
+                    out.push_str(&format!("{}  {}\n", indent, line.trim()));
+                }
+            }
+        }
+        self.list_depth -= 1;
+        if self.list_depth == 0 {
+            format!("\n{}\n", out)
+        } else {
+            out
+        }
+    }
+
+    fn table(&mut self, e: &Element) -> String {
+        let mut caption = None;
+        let mut rows = Vec::new();
+        self.table_rows(&e.children, &mut caption, &mut rows);
+        let width = rows.iter().map(|r: &Vec<String>| r.len()).max().unwrap_or(0);
+        let mut out = String::from("\n\n");
+        if let Some(caption) = caption {
+            if self.markdown {
+                out.push_str(&format!("**{}**\n\n", caption));
+            } else {
+                out.push_str(&format!("{}\n", caption));
+            }
+        }
+        for (i, mut row) in rows.into_iter().enumerate() {
+            if self.markdown {
+                row.resize(width, String::new());
+                out.push_str(&format!("| {} |\n", row.join(" | ")));
+                if i == 0 {
+                    out.push_str(&format!("|{}\n", " --- |".repeat(width)));
+                }
+            } else {
+                out.push_str(&format!("{}\n", row.join("\t")));
+            }
+        }
+        out.push('\n');
+        out
+    }
+
+    /// Collects the caption and the rows of cells of a table, looking into
+    /// `thead`, `tbody` and `tfoot`. Cells spanning several columns are
+    /// repeated.
+    fn table_rows(&mut self, nodes: &[HtmlNode], caption: &mut Option<String>, rows: &mut Vec<Vec<String>>) {
+        for node in nodes {
+            let e = match *node {
+                HtmlNode::Element(ref e) => e,
+                _ => continue,
+            };
+            match &*e.name {
+                "caption" => *caption = Some(self.inline(&e.children)),
+                "thead" | "tbody" | "tfoot" => self.table_rows(&e.children, caption, rows),
+                "tr" => {
+                    let mut row = Vec::new();
+                    for cell in &e.children {
+                        let cell = match *cell {
+                            HtmlNode::Element(ref c) if c.name == "td" || c.name == "th" => c,
+                            _ => continue,
+                        };
+                        let mut text = self.inline(&cell.children);
+                        if self.markdown {
+                            text = text.replace('|', "\\|");
+                        }
+                        let colspan = cell.attribute("colspan")
+                            .and_then(|c| c.trim().parse::<usize>().ok())
+                            .unwrap_or(1);
+                        for _ in 0..colspan.clamp(1, 1000) {
+                            row.push(text.clone());
+                        }
+                    }
+                    rows.push(row);
+                },
+                _ => (),
+            }
+        }
+    }
+}
+
+/// Replaces every run of whitespace with a single space.
+fn collapse_whitespace(text: &str) -> String {
+    let mut out = String::new();
+    let mut space = false;
+    for c in text.chars() {
+        if c.is_whitespace() && c != '\u{a0}' {
+            space = true;
+            continue;
+        }
+        if space {
+            out.push(' ');
+            space = false;
+        }
+        out.push(c);
+    }
+    if space {
+        out.push(' ');
+    }
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Html, HtmlNode};
+
+    const HTML: &str = "<div class=\"mw-parser-output\"><div role=\"note\" class=\"hatnote navigation-not-searchable\">Not to be confused with <a href=\"/wiki/Paris,_Texas\">Paris, Texas</a>.</div>\
+        <table class=\"infobox\"><caption>Paris</caption><tbody><tr><th>Country</th><td><a href=\"//en.wikipedia.org/wiki/France\">France</a></td></tr><tr><td colspan=\"2\">Capital &amp; city</td></tr></tbody></table>\n\
+        <p><b>Paris</b> is the <i>capital</i> of France.<sup id=\"cite_ref-1\" class=\"reference\"><a href=\"#cite_note-1\">[1]</a></sup><span style=\"display: none\">hidden</span></p>\n\
+        <h2><span class=\"mw-headline\" id=\"History\">History</span><span class=\"mw-editsection\">[<a href=\"/w/index.php?action=edit\">edit</a>]</span></h2>\n\
+        <ul><li>Ancient<ul><li>Lutetia</li></ul></li><li>Modern</ul>\n\
+        <div class=\"navbox\">Cities of France</div><div class=\"reflist\"><ol class=\"references\"><li>Source</li></ol></div></div>";
+
+    #[test]
+    fn parse() {
+        let html = Html::parse("<p class=x>a &lt;<br>b</p>c<!-- d -->");
+        assert_eq!(html.nodes.len(), 2);
+        assert_eq!(html.nodes[1], HtmlNode::Text("c".to_owned()));
+        match html.nodes[0] {
+            HtmlNode::Element(ref p) => {
+                assert_eq!(p.attribute("class"), Some("x"));
+                assert_eq!(p.children.len(), 3);
+                assert_eq!(p.children[0], HtmlNode::Text("a <".to_owned()));
+            },
+            ref n => panic!("unexpected {:?}", n),
+        }
+    }
+
+    #[test]
+    fn markdown() {
+        let markdown = Html::parse(HTML)
+            .clean()
+            .resolve_links("https://en.wikipedia.org/w/api.php")
+            .to_markdown();
+        assert_eq!(markdown, "Not to be confused with [Paris, Texas](https://en.wikipedia.org/wiki/Paris,_Texas).\n\n\
+            **Paris**\n\n\
+            | Country | [France](https://en.wikipedia.org/wiki/France) |\n\
+            | --- | --- |\n\
+            | Capital & city | Capital & city |\n\n\
+            **Paris** is the *capital* of France.\n\n\
+            ## History\n\n\
+            - Ancient\n  - Lutetia\n- Modern");
+    }
+
+    #[test]
+    fn plain_text() {
+        let text = Html::parse(HTML).clean().to_plain_text();
+        assert_eq!(text, "Not to be confused with Paris, Texas.\n\n\
+            Paris\n\
+            Country\tFrance\n\
+            Capital & city\tCapital & city\n\n\
+            Paris is the capital of France.\n\n\
+            History\n\n\
+            - Ancient\n  - Lutetia\n- Modern");
+    }
+
+    #[test]
+    fn non_ascii() {
+        let html = Html::parse("<p>École <b>«Ödön»</b> €5</p><SCRIPT>İ</SCRIPT>ü");
+        assert_eq!(html.to_plain_text(), "École «Ödön» €5\n\nü");
+    }
+}
@@ -1,5 +1,69 @@
+use std::error;
+use std::fmt;
+use std::sync::Arc;
+
+#[cfg(feature="async")]
+use futures::future::BoxFuture;
+
+pub mod cache;
+#[cfg(any(test, feature="mock"))] pub mod mock;
+pub mod record;
+pub mod retry;
+#[cfg(test)] mod testing;
+pub use self::cache::CachingClient;
+pub use self::record::{RecordingClient, ReplayClient};
+pub use self::retry::RetryClient;
+
+/// Failure performing an HTTP request.
 #[derive(Debug)]
-pub struct Error;
+pub struct Error {
+    /// Status code of the response, if the server answered.
+    pub status: Option<u16>,
+    /// Headers of the response, as (`name`, `value`) pairs.
+    pub headers: Vec<(String, String)>,
+    /// Underlying error that prevented the request from completing, if any.
+    pub source: Option<Box<dyn error::Error + Send + Sync>>,
+}
+
+impl Error {
+    /// Creates an error for a response with an unsuccessful `status`.
+    pub fn from_status(status: u16, headers: Vec<(String, String)>) -> Error {
+        Error { status: Some(status), headers, source: None }
+    }
+
+    /// Creates an error caused by `source`.
+    pub fn from_source<E>(source: E) -> Error
+            where E: Into<Box<dyn error::Error + Send + Sync>> {
+        Error { status: None, headers: Vec::new(), source: Some(source.into()) }
+    }
+
+    /// Returns the value of the header `name`, ignoring its case.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|h| h.0.eq_ignore_ascii_case(name))
+            .map(|h| &*h.1)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.status, &self.source) {
+            (Some(status), _) => write!(f, "HTTP request failed with status {}", status),
+            (None, Some(e)) => write!(f, "HTTP request failed: {}", e),
+            (None, &None) => write!(f, "HTTP request failed"),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self.source {
+            Some(ref e) => Some(&**e),
+            None => None,
+        }
+    }
+}
 
 pub trait HttpClient: Default {
     fn user_agent(&mut self, user_agent: String);
@@ -7,6 +71,37 @@ pub trait HttpClient: Default {
         where I: Iterator<Item=(&'a str, &'a str)>;
 }
 
+/// Lets several `Wikipedia` objects, for example one per language, use the
+/// same client and therefore the same cache or rate limits. See
+/// `Wikipedia::with_language`.
+impl<C: HttpClient> HttpClient for Arc<C> {
+    /// Sets the user agent of the shared client if this is the only pointer
+    /// to it, as when calling `Wikipedia::new(Arc::new(client))`.
+    ///
+    /// Once the client is shared this does nothing and the user agent
+    /// already set is kept, since changing it would affect every other
+    /// owner. Set the user agent before wrapping the client to use another.
+    fn user_agent(&mut self, user_agent: String) {
+        if let Some(client) = Arc::get_mut(self) {
+            client.user_agent(user_agent);
+        }
+    }
+
+    fn get<'a, I>(&self, base_url: &str, args: I) -> Result<String, Error>
+            where I: Iterator<Item=(&'a str, &'a str)> {
+        (**self).get(base_url, args)
+    }
+}
+
+/// Non-blocking counterpart of `HttpClient`. The returned future resolves
+/// to the response body.
+#[cfg(feature="async")]
+pub trait AsyncHttpClient: Default + Send + Sync {
+    fn user_agent(&mut self, user_agent: String);
+    fn get<'a>(&'a self, base_url: &str, args: Vec<(String, String)>)
+        -> BoxFuture<'a, Result<String, Error>>;
+}
+
 #[cfg(feature="http-client")]
 pub mod hyper {
     use std::convert;
@@ -18,6 +113,7 @@ pub mod hyper {
 
     use super::{Error, HttpClient};
 
+    #[derive(Clone)]
     pub struct Client {
         user_agent: String,
     }
@@ -43,7 +139,10 @@ pub mod hyper {
                 .send());
 
             if !response.status.is_success() {
-                return Err(Error);
+                return Err(Error::from_status(response.status.to_u16(), response.headers
+                    .iter()
+                    .map(|h| (h.name().to_owned(), h.value_string()))
+                    .collect()));
             }
 
             let mut response_str = String::new();
@@ -53,20 +152,20 @@ pub mod hyper {
     }
 
     impl convert::From<hyper::error::Error> for Error {
-        fn from(_: hyper::error::Error) -> Self {
-            Error
+        fn from(e: hyper::error::Error) -> Self {
+            Error::from_source(e)
         }
     }
 
     impl convert::From<url::ParseError> for Error {
-        fn from(_: url::ParseError) -> Self {
-            Error
+        fn from(e: url::ParseError) -> Self {
+            Error::from_source(e)
         }
     }
 
     impl convert::From<io::Error> for Error {
-        fn from(_: io::Error) -> Self {
-            Error
+        fn from(e: io::Error) -> Self {
+            Error::from_source(e)
         }
     }
 }
@@ -0,0 +1,348 @@
+//! Caching of responses, see `CachingClient`.
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use std::env;
+
+use serde_json;
+use serde_json::Value;
+
+use super::{Error, HttpClient};
+
+/// Storage for the responses saved by a `CachingClient`.
+pub trait CacheStore {
+    /// Returns the response stored for `key`, unless it expired.
+    fn get(&self, key: &str) -> Option<String>;
+    /// Stores `response` for `key` during `ttl`.
+    fn set(&self, key: &str, response: &str, ttl: Duration);
+}
+
+/// An `HttpClient` that wraps another one and reuses its responses.
+///
+/// Requests are identified by their base url and their arguments in any
+/// order. A response is kept for the `maxage` or `smaxage` requested, in
+/// seconds, or for `ttl` if the request has neither. Errors are never cached,
+/// including API errors reported in the response body, and neither are
+/// random results unless `cache_random` is set.
+#[derive(Debug)]
+pub struct CachingClient<A: HttpClient, S: CacheStore = MemoryStore> {
+    pub client: A,
+    pub store: S,
+    /// How long to keep responses to requests without `maxage` or `smaxage`.
+    pub ttl: Duration,
+    /// Whether to cache requests for random pages, such as the ones sent by
+    /// `Wikipedia::random`, which would otherwise return the same pages
+    /// until they expire.
+    pub cache_random: bool,
+}
+
+impl<A: HttpClient, S: CacheStore> CachingClient<A, S> {
+    /// Creates a client that caches the responses of `client` in `store`.
+    pub fn new(client: A, store: S) -> Self {
+        CachingClient {
+            client,
+            store,
+            ttl: Duration::from_secs(60 * 60),
+            cache_random: false,
+        }
+    }
+}
+
+impl<A: HttpClient, S: CacheStore + Default> Default for CachingClient<A, S> {
+    fn default() -> Self {
+        CachingClient::new(A::default(), S::default())
+    }
+}
+
+impl<A: HttpClient, S: CacheStore + Default> HttpClient for CachingClient<A, S> {
+    fn user_agent(&mut self, user_agent: String) {
+        self.client.user_agent(user_agent)
+    }
+
+    fn get<'a, I>(&self, base_url: &str, args: I) -> Result<String, Error>
+            where I: Iterator<Item=(&'a str, &'a str)> {
+        let args: Vec<_> = args.collect();
+        let mut sorted = args.clone();
+        sorted.sort();
+        let key = sorted.iter().fold(base_url.to_owned(), |key, &(k, v)| {
+            format!("{}\n{}={}", key, k, v)
+        });
+        if !self.cache_random && is_random(&sorted) {
+            return self.client.get(base_url, args.into_iter());
+        }
+        if let Some(response) = self.store.get(&key) {
+            return Ok(response);
+        }
+
+        let response = try!(self.client.get(base_url, args.into_iter()));
+        if is_api_error(&response) {
+            return Ok(response);
+        }
+        let ttl = match sorted.iter()
+                .filter(|&&(k, _)| k == "maxage" || k == "smaxage")
+                .filter_map(|&(_, v)| v.parse().ok())
+                .max() {
+            Some(seconds) => Duration::from_secs(seconds),
+            None => self.ttl,
+        };
+        if ttl > Duration::from_secs(0) {
+            self.store.set(&key, &response, ttl);
+        }
+        Ok(response)
+    }
+}
+
+/// Returns true if the request lists or generates random pages.
+fn is_random(args: &[(&str, &str)]) -> bool {
+    args.iter().any(|&(k, v)| (k == "list" || k == "generator") && v.split('|').any(|v| v == "random"))
+}
+
+/// Returns true if `response` reports an API error, like `maxlag`.
+fn is_api_error(response: &str) -> bool {
+    if !response.contains("\"error\"") {
+        return false;
+    }
+    serde_json::from_str::<Value>(response)
+        .ok()
+        .and_then(|v| v.as_object().map(|o| o.contains_key("error")))
+        .unwrap_or(false)
+}
+
+#[derive(Debug)]
+struct MemoryEntry {
+    response: String,
+    expires: Instant,
+    used: u64,
+}
+
+#[derive(Debug, Default)]
+struct MemoryEntries {
+    entries: HashMap<String, MemoryEntry>,
+    /// Keys by the last time they were used, oldest first.
+    usage: BTreeMap<u64, String>,
+    clock: u64,
+}
+
+/// A `CacheStore` that keeps up to `capacity` responses in memory, dropping
+/// the least recently used one when full.
+#[derive(Debug)]
+pub struct MemoryStore {
+    capacity: usize,
+    entries: Mutex<MemoryEntries>,
+}
+
+impl MemoryStore {
+    pub fn new(capacity: usize) -> MemoryStore {
+        MemoryStore { capacity, entries: Mutex::new(MemoryEntries::default()) }
+    }
+}
+
+impl Default for MemoryStore {
+    fn default() -> Self {
+        MemoryStore::new(1000)
+    }
+}
+
+impl CacheStore for MemoryStore {
+    fn get(&self, key: &str) -> Option<String> {
+        let mut guard = self.entries.lock().unwrap();
+        let e = &mut *guard;
+        let expired = match e.entries.get(key) {
+            Some(entry) => entry.expires <= Instant::now(),
+            None => return None,
+        };
+        let entry = e.entries.get_mut(key).unwrap();
+        e.usage.remove(&entry.used);
+        if expired {
+            e.entries.remove(key);
+            return None;
+        }
+        e.clock += 1;
+        entry.used = e.clock;
+        e.usage.insert(e.clock, key.to_owned());
+        Some(entry.response.clone())
+    }
+
+    fn set(&self, key: &str, response: &str, ttl: Duration) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut guard = self.entries.lock().unwrap();
+        let e = &mut *guard;
+        if let Some(old) = e.entries.remove(key) {
+            e.usage.remove(&old.used);
+        }
+        while e.entries.len() >= self.capacity {
+            let oldest = match e.usage.keys().next() {
+                Some(&used) => used,
+                None => break,
+            };
+            if let Some(k) = e.usage.remove(&oldest) {
+                e.entries.remove(&*k);
+            }
+        }
+        e.clock += 1;
+        e.usage.insert(e.clock, key.to_owned());
+        e.entries.insert(key.to_owned(), MemoryEntry {
+            response: response.to_owned(),
+            expires: Instant::now() + ttl,
+            used: e.clock,
+        });
+    }
+}
+
+/// A `CacheStore` that saves every response in a file inside `directory`,
+/// so it can be shared across runs.
+#[derive(Debug)]
+pub struct DiskStore {
+    pub directory: PathBuf,
+}
+
+impl DiskStore {
+    /// Creates a store in `directory`, which is created if needed.
+    pub fn new<P: Into<PathBuf>>(directory: P) -> DiskStore {
+        DiskStore { directory: directory.into() }
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        // FNV-1a, which unlike `DefaultHasher` is stable across releases
+        let hash = key.bytes().fold(0xcbf29ce484222325u64, |h, b| {
+            (h ^ b as u64).wrapping_mul(0x100000001b3)
+        });
+        self.directory.join(format!("{:016x}.json", hash))
+    }
+}
+
+impl Default for DiskStore {
+    fn default() -> Self {
+        DiskStore::new(env::temp_dir().join("wikipedia-rs-cache"))
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+impl CacheStore for DiskStore {
+    fn get(&self, key: &str) -> Option<String> {
+        let mut contents = String::new();
+        match fs::File::open(self.path(key)).and_then(|mut f| f.read_to_string(&mut contents)) {
+            Ok(_) => (),
+            Err(_) => return None,
+        }
+        let value: Value = match serde_json::from_str(&contents) {
+            Ok(v) => v,
+            Err(_) => return None,
+        };
+        let entry = match value.as_object() {
+            Some(e) => e,
+            None => return None,
+        };
+        // different keys may share a file if their hashes collide
+        if entry.get("key").and_then(|x| x.as_string()) != Some(key) {
+            return None;
+        }
+        if entry.get("expires").and_then(|x| x.as_u64()).unwrap_or(0) <= now() {
+            return None;
+        }
+        entry.get("response").and_then(|x| x.as_string()).map(|x| x.to_owned())
+    }
+
+    fn set(&self, key: &str, response: &str, ttl: Duration) {
+        let mut entry = BTreeMap::new();
+        entry.insert("key".to_owned(), Value::String(key.to_owned()));
+        entry.insert("expires".to_owned(), Value::U64(now() + ttl.as_secs()));
+        entry.insert("response".to_owned(), Value::String(response.to_owned()));
+        let contents = match serde_json::to_string(&Value::Object(entry)) {
+            Ok(c) => c,
+            Err(_) => return,
+        };
+        // a cache that cannot be written is just a cache miss next time
+        let _ = fs::create_dir_all(&self.directory)
+            .and_then(|_| fs::File::create(self.path(key)))
+            .and_then(|mut f| f.write_all(contents.as_bytes()));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{CacheStore, CachingClient, DiskStore, MemoryStore};
+    use super::super::HttpClient;
+    use super::super::testing::CountingClient;
+    use std::env;
+    use std::fs;
+    use std::time::Duration;
+
+    #[test]
+    fn cache_hit() {
+        let client = CachingClient::<CountingClient>::default();
+        let r1 = client.get("http://a", vec![("a", "1"), ("b", "2")].into_iter()).unwrap();
+        let r2 = client.get("http://a", vec![("b", "2"), ("a", "1")].into_iter()).unwrap();
+        let r3 = client.get("http://b", vec![("b", "2"), ("a", "1")].into_iter()).unwrap();
+        assert_eq!(r1, "response 1".to_owned());
+        assert_eq!(r2, "response 1".to_owned());
+        assert_eq!(r3, "response 2".to_owned());
+        assert_eq!(*client.client.requests.lock().unwrap(), 2);
+    }
+
+    #[test]
+    fn maxage() {
+        let client = CachingClient::<CountingClient>::default();
+        client.get("http://a", vec![("maxage", "0")].into_iter()).unwrap();
+        client.get("http://a", vec![("maxage", "0")].into_iter()).unwrap();
+        assert_eq!(*client.client.requests.lock().unwrap(), 2);
+    }
+
+    #[test]
+    fn api_error() {
+        let mut client = CachingClient::<CountingClient>::default();
+        client.client.response = Some("{\"error\":{\"code\":\"maxlag\",\"lag\":1}}".to_owned());
+        client.get("http://a", vec![].into_iter()).unwrap();
+        client.get("http://a", vec![].into_iter()).unwrap();
+        assert_eq!(*client.client.requests.lock().unwrap(), 2);
+    }
+
+    #[test]
+    fn random() {
+        let mut client = CachingClient::<CountingClient>::default();
+        let r1 = client.get("http://a", vec![("list", "random")].into_iter()).unwrap();
+        let r2 = client.get("http://a", vec![("list", "random")].into_iter()).unwrap();
+        assert_eq!((r1, r2), ("response 1".to_owned(), "response 2".to_owned()));
+        client.cache_random = true;
+        client.get("http://a", vec![("generator", "random")].into_iter()).unwrap();
+        client.get("http://a", vec![("generator", "random")].into_iter()).unwrap();
+        assert_eq!(*client.client.requests.lock().unwrap(), 3);
+    }
+
+    #[test]
+    fn memory_lru() {
+        let store = MemoryStore::new(2);
+        let ttl = Duration::from_secs(60);
+        store.set("a", "1", ttl);
+        store.set("b", "2", ttl);
+        assert_eq!(store.get("a"), Some("1".to_owned()));
+        store.set("c", "3", ttl);
+        assert_eq!(store.get("b"), None);
+        assert_eq!(store.get("a"), Some("1".to_owned()));
+        assert_eq!(store.get("c"), Some("3".to_owned()));
+
+        store.set("a", "1", Duration::from_secs(0));
+        assert_eq!(store.get("a"), None);
+    }
+
+    #[test]
+    fn disk() {
+        let directory = env::temp_dir().join(format!("wikipedia-rs-test-{}", ::std::process::id()));
+        let store = DiskStore::new(directory.clone());
+        assert_eq!(store.get("a"), None);
+        store.set("a", "hello", Duration::from_secs(60));
+        assert_eq!(DiskStore::new(directory.clone()).get("a"), Some("hello".to_owned()));
+        assert_eq!(store.get("b"), None);
+        store.set("b", "world", Duration::from_secs(0));
+        assert_eq!(store.get("b"), None);
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -0,0 +1,239 @@
+//! A configurable `HttpClient` for testing code built on `Wikipedia`,
+//! enabled by the `mock` feature. With the `async` feature it is also an
+//! `AsyncHttpClient`.
+//!
+//! # Examples
+//!
+//! ```
+//! extern crate wikipedia;
+//!
+//! use wikipedia::Wikipedia;
+//! use wikipedia::http::mock::MockClient;
+//!
+//! let wikipedia = Wikipedia::<MockClient>::default();
+//! wikipedia.client.respond_to(&[("list", "search")],
+//!     "{\"query\":{\"search\":[{\"title\":\"hello\"}]}}");
+//! assert_eq!(wikipedia.search("hello").unwrap(), vec!["hello".to_owned()]);
+//! wikipedia.client.assert_requested(&[("srsearch", "hello")]);
+//! ```
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+#[cfg(feature="async")]
+use futures::future::{self, BoxFuture, FutureExt};
+
+use super::{Error, HttpClient};
+#[cfg(feature="async")]
+use super::AsyncHttpClient;
+
+/// What a `MockClient` answers to a request.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Reply {
+    /// A successful response with this body.
+    Body(String),
+    /// A response with an unsuccessful status code and headers.
+    Status(u16, Vec<(String, String)>),
+    /// A failure to communicate with the server, described by the message.
+    Failure(String),
+}
+
+impl Reply {
+    fn into_result(self) -> Result<String, Error> {
+        match self {
+            Reply::Body(b) => Ok(b),
+            Reply::Status(status, headers) => Err(Error::from_status(status, headers)),
+            Reply::Failure(message) => Err(Error::from_source(message)),
+        }
+    }
+}
+
+/// A request received by a `MockClient`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Request {
+    pub base_url: String,
+    pub args: Vec<(String, String)>,
+}
+
+impl Request {
+    /// Returns the value of the argument `name`, if present.
+    pub fn arg(&self, name: &str) -> Option<&str> {
+        self.args.iter().find(|a| a.0 == name).map(|a| &*a.1)
+    }
+
+    /// Returns whether the request has all the arguments in `args`.
+    pub fn matches(&self, args: &[(&str, &str)]) -> bool {
+        args.iter().all(|&(k, v)| self.args.iter().any(|a| a.0 == k && a.1 == v))
+    }
+}
+
+#[derive(Debug)]
+struct Rule {
+    args: Vec<(String, String)>,
+    replies: VecDeque<Reply>,
+}
+
+/// An `HttpClient` that answers with queued replies and records requests.
+///
+/// Each reply is queued for the requests that have a set of arguments, or
+/// for any request if the set is empty. A request takes the next reply
+/// queued for the largest set it matches. Requests without replies fail.
+#[derive(Debug, Default)]
+pub struct MockClient {
+    /// Last user agent set.
+    pub user_agent: Option<String>,
+    rules: Mutex<Vec<Rule>>,
+    requests: Mutex<Vec<Request>>,
+}
+
+impl MockClient {
+    /// Queues `reply` for requests that have all the arguments in `args`.
+    pub fn push(&self, args: &[(&str, &str)], reply: Reply) {
+        let args: Vec<_> = args.iter().map(|&(k, v)| (k.to_owned(), v.to_owned())).collect();
+        let mut rules = self.rules.lock().unwrap();
+        if let Some(rule) = rules.iter_mut().find(|r| r.args == args) {
+            rule.replies.push_back(reply);
+            return;
+        }
+        let mut replies = VecDeque::new();
+        replies.push_back(reply);
+        rules.push(Rule { args, replies });
+    }
+
+    /// Queues a response with `body` for any request.
+    pub fn respond(&self, body: &str) {
+        self.push(&[], Reply::Body(body.to_owned()))
+    }
+
+    /// Queues a response with `body` for requests that have all the
+    /// arguments in `args`.
+    pub fn respond_to(&self, args: &[(&str, &str)], body: &str) {
+        self.push(args, Reply::Body(body.to_owned()))
+    }
+
+    /// Queues a response with `body` for requests with the `action` argument.
+    pub fn respond_to_action(&self, action: &str, body: &str) {
+        self.respond_to(&[("action", action)], body)
+    }
+
+    /// Queues a failed response with `status` for any request.
+    pub fn fail(&self, status: u16) {
+        self.push(&[], Reply::Status(status, Vec::new()))
+    }
+
+    /// Returns all requests received so far.
+    pub fn requests(&self) -> Vec<Request> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// Returns the number of replies not used yet.
+    pub fn pending(&self) -> usize {
+        self.rules.lock().unwrap().iter().map(|r| r.replies.len()).sum()
+    }
+
+    /// Panics unless a request with all the arguments in `args` was received.
+    pub fn assert_requested(&self, args: &[(&str, &str)]) {
+        let requests = self.requests();
+        if !requests.iter().any(|r| r.matches(args)) {
+            panic!("no request matched {:?}, got {:?}", args, requests);
+        }
+    }
+
+    /// Panics unless the user agent is `user_agent`.
+    pub fn assert_user_agent(&self, user_agent: &str) {
+        assert_eq!(self.user_agent.as_deref(), Some(user_agent));
+    }
+
+    /// Panics if some queued reply was not used.
+    pub fn assert_all_used(&self) {
+        let pending = self.pending();
+        if pending > 0 {
+            panic!("{} replies were not used", pending);
+        }
+    }
+}
+
+impl HttpClient for MockClient {
+    fn user_agent(&mut self, user_agent: String) {
+        self.user_agent = Some(user_agent)
+    }
+
+    fn get<'a, I>(&self, base_url: &str, args: I) -> Result<String, Error>
+            where I: Iterator<Item=(&'a str, &'a str)> {
+        let request = Request {
+            base_url: base_url.to_owned(),
+            args: args.map(|(k, v)| (k.to_owned(), v.to_owned())).collect(),
+        };
+        self.requests.lock().unwrap().push(request.clone());
+        let mut rules = self.rules.lock().unwrap();
+        let mut best: Option<&mut Rule> = None;
+        for rule in rules.iter_mut() {
+            if rule.replies.is_empty() || !rule.args.iter().all(|a| request.args.contains(a)) {
+                continue;
+            }
+            if best.as_ref().map(|b| b.args.len() < rule.args.len()).unwrap_or(true) {
+                best = Some(rule);
+            }
+        }
+        let reply = best.and_then(|r| r.replies.pop_front());
+        match reply {
+            Some(reply) => reply.into_result(),
+            None => Err(Error::from_source(format!("no reply for {:?}", request))),
+        }
+    }
+}
+
+#[cfg(feature="async")]
+impl AsyncHttpClient for MockClient {
+    fn user_agent(&mut self, user_agent: String) {
+        HttpClient::user_agent(self, user_agent)
+    }
+
+    fn get<'a>(&'a self, base_url: &str, args: Vec<(String, String)>)
+            -> BoxFuture<'a, Result<String, Error>> {
+        let response = HttpClient::get(self, base_url, args.iter().map(|(k, v)| (&**k, &**v)));
+        future::ready(response).boxed()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{MockClient, Reply};
+    use super::super::super::{Error, Wikipedia};
+
+    #[test]
+    fn replies_by_arguments() {
+        let wikipedia = Wikipedia::<MockClient>::default();
+        wikipedia.client.respond("{\"query\":{\"random\":[{\"title\":\"any\"}]}}");
+        wikipedia.client.respond_to(&[("list", "search")], "{\"query\":{\"search\":[{\"title\":\"hello\"}]}}");
+        assert_eq!(wikipedia.search("hello").unwrap(), vec!["hello".to_owned()]);
+        assert_eq!(wikipedia.random().unwrap(), Some("any".to_owned()));
+        assert!(wikipedia.random().is_err());
+        wikipedia.client.assert_all_used();
+        wikipedia.client.assert_requested(&[("list", "search"), ("srsearch", "hello")]);
+        wikipedia.client.assert_user_agent("wikipedia (https://github.com/seppo0010/wikipedia-rs)");
+        assert_eq!(wikipedia.client.requests().len(), 3);
+        assert_eq!(wikipedia.client.requests()[1].arg("list"), Some("random"));
+    }
+
+    #[test]
+    fn failures() {
+        let wikipedia = Wikipedia::<MockClient>::default();
+        wikipedia.client.fail(503);
+        wikipedia.client.push(&[], Reply::Failure("connection reset".to_owned()));
+        match wikipedia.search("hello").unwrap_err() {
+            Error::HTTPError(e) => assert_eq!(e.status, Some(503)),
+            e => panic!("unexpected error {:?}", e),
+        }
+        match wikipedia.search("hello").unwrap_err() {
+            Error::HTTPError(e) => assert_eq!(format!("{}", e), "HTTP request failed: connection reset"),
+            e => panic!("unexpected error {:?}", e),
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn assert_requested() {
+        let client = MockClient::default();
+        client.assert_requested(&[("action", "query")]);
+    }
+}
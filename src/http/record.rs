@@ -0,0 +1,334 @@
+//! Recording and replaying of requests for offline tests, see
+//! `RecordingClient` and `ReplayClient`.
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use serde_json;
+use serde_json::Value;
+
+use super::{Error, HttpClient};
+
+/// A request and the response it got.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Interaction {
+    pub base_url: String,
+    pub args: Vec<(String, String)>,
+    pub response: String,
+}
+
+impl Interaction {
+    fn matches(&self, base_url: &str, args: &[(String, String)]) -> bool {
+        let mut a = self.args.clone();
+        let mut b = args.to_vec();
+        a.sort();
+        b.sort();
+        self.base_url == base_url && a == b
+    }
+
+    fn to_value(&self) -> Value {
+        let mut obj = BTreeMap::new();
+        obj.insert("base_url".to_owned(), Value::String(self.base_url.clone()));
+        obj.insert("args".to_owned(), Value::Array(self.args
+            .iter()
+            .map(|(k, v)| Value::Array(vec![
+                Value::String(k.clone()),
+                Value::String(v.clone()),
+            ]))
+            .collect()));
+        obj.insert("response".to_owned(), Value::String(self.response.clone()));
+        Value::Object(obj)
+    }
+
+    fn from_value(value: &Value) -> Option<Interaction> {
+        let obj = match value.as_object() {
+            Some(o) => o,
+            None => return None,
+        };
+        let mut args = vec![];
+        for arg in obj.get("args").and_then(|x| x.as_array()).into_iter().flatten() {
+            let pair = match arg.as_array() {
+                Some(p) if p.len() == 2 => p,
+                _ => return None,
+            };
+            match (pair[0].as_string(), pair[1].as_string()) {
+                (Some(k), Some(v)) => args.push((k.to_owned(), v.to_owned())),
+                _ => return None,
+            }
+        }
+        Some(Interaction {
+            base_url: match obj.get("base_url").and_then(|x| x.as_string()) {
+                Some(u) => u.to_owned(),
+                None => return None,
+            },
+            args,
+            response: match obj.get("response").and_then(|x| x.as_string()) {
+                Some(r) => r.to_owned(),
+                None => return None,
+            },
+        })
+    }
+}
+
+/// A list of interactions, stored as a JSON fixture file.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Cassette {
+    pub interactions: Vec<Interaction>,
+}
+
+impl Cassette {
+    /// Reads a cassette from `path`.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Cassette> {
+        let mut contents = String::new();
+        try!(try!(fs::File::open(path)).read_to_string(&mut contents));
+        let value: Value = try!(serde_json::from_str(&contents)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)));
+        let interactions = try!(value
+            .as_array()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "expected an array")));
+        Ok(Cassette {
+            interactions: try!(interactions
+                .iter()
+                .map(|x| Interaction::from_value(x)
+                     .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid interaction")))
+                .collect::<io::Result<_>>()),
+        })
+    }
+
+    /// Like `load`, but returns an empty cassette if `path` does not exist.
+    pub fn load_or_default<P: AsRef<Path>>(path: P) -> io::Result<Cassette> {
+        match Cassette::load(path) {
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(Cassette::default()),
+            r => r,
+        }
+    }
+
+    /// Writes the cassette to `path`, replacing it.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let value = Value::Array(self.interactions.iter().map(|x| x.to_value()).collect());
+        let contents = try!(serde_json::to_string_pretty(&value)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)));
+        try!(fs::File::create(path)).write_all(contents.as_bytes())
+    }
+}
+
+/// An `HttpClient` that sends requests with another client and saves every
+/// successful one in a cassette file, to be replayed by `ReplayClient`.
+#[derive(Debug)]
+pub struct RecordingClient<A: HttpClient> {
+    pub client: A,
+    path: Option<PathBuf>,
+    cassette: Mutex<Cassette>,
+}
+
+impl<A: HttpClient> RecordingClient<A> {
+    /// Creates a client that records the requests sent with `client` to
+    /// `path`. The file is rewritten after every request.
+    pub fn new<P: Into<PathBuf>>(client: A, path: P) -> Self {
+        RecordingClient {
+            client,
+            path: Some(path.into()),
+            cassette: Mutex::new(Cassette::default()),
+        }
+    }
+
+    /// Like `new`, but keeps the interactions already saved in `path`, if
+    /// any, and adds the new ones after them.
+    pub fn append<P: Into<PathBuf>>(client: A, path: P) -> io::Result<Self> {
+        let path = path.into();
+        let cassette = try!(Cassette::load_or_default(&path));
+        Ok(RecordingClient { client, path: Some(path), cassette: Mutex::new(cassette) })
+    }
+
+    /// Returns a copy of the interactions recorded so far.
+    pub fn cassette(&self) -> Cassette {
+        self.cassette.lock().unwrap().clone()
+    }
+}
+
+impl<A: HttpClient> Default for RecordingClient<A> {
+    /// Creates a client that records in memory only, see `cassette`.
+    fn default() -> Self {
+        RecordingClient {
+            client: A::default(),
+            path: None,
+            cassette: Mutex::new(Cassette::default()),
+        }
+    }
+}
+
+impl<A: HttpClient> HttpClient for RecordingClient<A> {
+    fn user_agent(&mut self, user_agent: String) {
+        self.client.user_agent(user_agent)
+    }
+
+    fn get<'a, I>(&self, base_url: &str, args: I) -> Result<String, Error>
+            where I: Iterator<Item=(&'a str, &'a str)> {
+        let args: Vec<_> = args.collect();
+        let response = try!(self.client.get(base_url, args.iter().cloned()));
+        let mut cassette = self.cassette.lock().unwrap();
+        cassette.interactions.push(Interaction {
+            base_url: base_url.to_owned(),
+            args: args.iter().map(|&(k, v)| (k.to_owned(), v.to_owned())).collect(),
+            response: response.clone(),
+        });
+        if let Some(ref path) = self.path {
+            try!(cassette.save(path).map_err(Error::from_source));
+        }
+        Ok(response)
+    }
+}
+
+/// An `HttpClient` that fails every request, used by `ReplayClient` when
+/// no other client is given.
+#[derive(Debug, Default)]
+pub struct OfflineClient;
+
+impl HttpClient for OfflineClient {
+    fn user_agent(&mut self, _: String) {}
+
+    fn get<'a, I>(&self, base_url: &str, _: I) -> Result<String, Error>
+            where I: Iterator<Item=(&'a str, &'a str)> {
+        Err(Error::from_source(format!("offline, not sending request to {}", base_url)))
+    }
+}
+
+/// An `HttpClient` that answers with the responses in a cassette.
+///
+/// Requests match an interaction when they have the same base url and
+/// arguments, in any order. Identical requests get the responses recorded
+/// for them in order, repeating the last one when they run out.
+///
+/// Requests that are not in the cassette fail if `strict` is set, and are
+/// otherwise sent with `client`. See `record_new` to replay a cassette and
+/// record the requests missing from it.
+#[derive(Debug)]
+pub struct ReplayClient<A: HttpClient = OfflineClient> {
+    pub client: A,
+    pub strict: bool,
+    cassette: Cassette,
+    used: Mutex<Vec<bool>>,
+}
+
+impl<A: HttpClient> ReplayClient<A> {
+    /// Creates a strict client that replays `cassette`.
+    pub fn new(client: A, cassette: Cassette) -> Self {
+        let used = vec![false; cassette.interactions.len()];
+        ReplayClient { client, strict: true, cassette, used: Mutex::new(used) }
+    }
+
+    /// Creates a strict client that replays the cassette saved in `path`.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(ReplayClient::new(A::default(), try!(Cassette::load(path))))
+    }
+}
+
+impl<A: HttpClient> ReplayClient<RecordingClient<A>> {
+    /// Creates a non-strict client that replays the cassette saved in
+    /// `path`, if any, and sends the requests missing from it with `client`,
+    /// adding them to the same file.
+    pub fn record_new<P: Into<PathBuf>>(client: A, path: P) -> io::Result<Self> {
+        let recorder = try!(RecordingClient::append(client, path));
+        let cassette = recorder.cassette();
+        let mut replay = ReplayClient::new(recorder, cassette);
+        replay.strict = false;
+        Ok(replay)
+    }
+}
+
+impl<A: HttpClient> Default for ReplayClient<A> {
+    fn default() -> Self {
+        ReplayClient::new(A::default(), Cassette::default())
+    }
+}
+
+impl<A: HttpClient> HttpClient for ReplayClient<A> {
+    fn user_agent(&mut self, user_agent: String) {
+        self.client.user_agent(user_agent)
+    }
+
+    fn get<'a, I>(&self, base_url: &str, args: I) -> Result<String, Error>
+            where I: Iterator<Item=(&'a str, &'a str)> {
+        let args: Vec<_> = args.map(|(k, v)| (k.to_owned(), v.to_owned())).collect();
+        let mut used = self.used.lock().unwrap();
+        let matching: Vec<usize> = self.cassette.interactions
+            .iter()
+            .enumerate()
+            .filter(|&(_, x)| x.matches(base_url, &args))
+            .map(|(i, _)| i)
+            .collect();
+        let index = matching.iter().cloned().find(|&i| !used[i]).or(matching.last().cloned());
+        match index {
+            Some(i) => {
+                used[i] = true;
+                Ok(self.cassette.interactions[i].response.clone())
+            },
+            None if self.strict => Err(Error::from_source(format!(
+                "unrecorded request to {} with {:?}", base_url, args))),
+            None => self.client.get(base_url, args.iter().map(|(k, v)| (&**k, &**v))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Cassette, Interaction, RecordingClient, ReplayClient};
+    use super::super::HttpClient;
+    use super::super::testing::CountingClient;
+    use std::env;
+    use std::fs;
+
+    #[test]
+    fn record_and_replay() {
+        let path = env::temp_dir().join(format!("wikipedia-rs-cassette-{}.json", ::std::process::id()));
+        let client = RecordingClient::new(CountingClient::default(), path.clone());
+        client.get("http://a", vec![("a", "1"), ("b", "2")].into_iter()).unwrap();
+        client.get("http://a", vec![("a", "1"), ("b", "2")].into_iter()).unwrap();
+        client.get("http://b", vec![].into_iter()).unwrap();
+
+        let cassette = Cassette::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(cassette, client.cassette());
+        assert_eq!(cassette.interactions[2], Interaction {
+            base_url: "http://b".to_owned(),
+            args: vec![],
+            response: "response 3".to_owned(),
+        });
+
+        let replay = ReplayClient::<CountingClient>::new(CountingClient::default(), cassette);
+        assert_eq!(replay.get("http://a", vec![("b", "2"), ("a", "1")].into_iter()).unwrap(), "response 1".to_owned());
+        assert_eq!(replay.get("http://a", vec![("a", "1"), ("b", "2")].into_iter()).unwrap(), "response 2".to_owned());
+        assert_eq!(replay.get("http://a", vec![("a", "1"), ("b", "2")].into_iter()).unwrap(), "response 2".to_owned());
+        assert_eq!(replay.get("http://b", vec![].into_iter()).unwrap(), "response 3".to_owned());
+        assert_eq!(*replay.client.requests.lock().unwrap(), 0);
+    }
+
+    #[test]
+    fn unrecorded() {
+        let mut replay = ReplayClient::<CountingClient>::default();
+        assert!(replay.get("http://a", vec![].into_iter()).is_err());
+        replay.strict = false;
+        assert_eq!(replay.get("http://a", vec![].into_iter()).unwrap(), "response 1".to_owned());
+        assert!(ReplayClient::<super::OfflineClient>::default().get("http://a", vec![].into_iter()).is_err());
+    }
+
+    #[test]
+    fn record_new() {
+        let path = env::temp_dir().join(format!("wikipedia-rs-record-new-{}.json", ::std::process::id()));
+        let replay = ReplayClient::record_new(CountingClient::default(), path.clone()).unwrap();
+        assert_eq!(replay.get("http://a", vec![].into_iter()).unwrap(), "response 1".to_owned());
+
+        let replay = ReplayClient::record_new(CountingClient::default(), path.clone()).unwrap();
+        assert_eq!(replay.get("http://a", vec![].into_iter()).unwrap(), "response 1".to_owned());
+        assert_eq!(replay.get("http://b", vec![].into_iter()).unwrap(), "response 1".to_owned());
+        assert_eq!(*replay.client.client.requests.lock().unwrap(), 1);
+
+        let cassette = Cassette::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(cassette.interactions.iter().map(|x| &*x.base_url).collect::<Vec<_>>(),
+                vec!["http://a", "http://b"]);
+    }
+}
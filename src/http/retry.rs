@@ -0,0 +1,229 @@
+//! Retries and rate limiting, see `RetryClient`.
+use std::cmp;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde_json;
+use serde_json::Value;
+
+use super::{Error, HttpClient};
+
+/// An `HttpClient` that wraps another one, limiting how often requests are
+/// sent and retrying the ones that fail temporarily.
+///
+/// A request is retried when the server answers 429 or 503, or when the API
+/// reports a `maxlag` error. It waits for as long as the server asks, that is
+/// `Retry-After` seconds or the reported `lag`, even beyond `max_backoff`, or
+/// else for an exponentially growing `backoff`.
+#[derive(Debug)]
+pub struct RetryClient<A: HttpClient> {
+    pub client: A,
+    /// Number of times a request is retried before giving up.
+    pub max_retries: u32,
+    /// Time to wait before the first retry. It doubles on every retry.
+    pub backoff: Duration,
+    /// Longest time to wait before a retry when the server does not say how
+    /// long to wait.
+    pub max_backoff: Duration,
+    /// Maximum number of requests to send per second, if any.
+    pub requests_per_second: Option<f64>,
+    last_request: Mutex<Option<Instant>>,
+}
+
+impl<A: HttpClient> RetryClient<A> {
+    /// Creates a client that retries requests sent with `client`.
+    pub fn new(client: A) -> Self {
+        RetryClient {
+            client,
+            max_retries: 5,
+            backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+            requests_per_second: None,
+            last_request: Mutex::new(None),
+        }
+    }
+
+    /// Sleeps until sending another request does not exceed
+    /// `requests_per_second`.
+    fn throttle(&self) {
+        let mut last_request = self.last_request.lock().unwrap();
+        if let (Some(rps), Some(last)) = (self.requests_per_second, *last_request) {
+            if rps > 0.0 {
+                let interval = Duration::from_nanos((1_000_000_000f64 / rps) as u64);
+                let elapsed = last.elapsed();
+                if elapsed < interval {
+                    thread::sleep(interval - elapsed);
+                }
+            }
+        }
+        *last_request = Some(Instant::now());
+    }
+}
+
+impl<A: HttpClient> Default for RetryClient<A> {
+    fn default() -> Self {
+        RetryClient::new(A::default())
+    }
+}
+
+/// Returns how long to wait before retrying a request that failed with
+/// `result`, or `None` if it should not be retried.
+fn retry_after(result: &Result<String, Error>) -> Option<Option<Duration>> {
+    match *result {
+        Ok(ref response) => {
+            if !response.contains("maxlag") {
+                return None;
+            }
+            let value: Value = match serde_json::from_str(response) {
+                Ok(v) => v,
+                Err(_) => return None,
+            };
+            let error = match value.as_object().and_then(|x| x.get("error")).and_then(|x| x.as_object()) {
+                Some(e) => e,
+                None => return None,
+            };
+            if error.get("code").and_then(|x| x.as_string()) != Some("maxlag") {
+                return None;
+            }
+            Some(error
+                .get("lag")
+                .and_then(|x| x.as_f64())
+                .filter(|&lag| lag >= 0.0 && lag.is_finite())
+                .map(|lag| Duration::from_millis((lag * 1000.0) as u64)))
+        },
+        Err(ref e) => match e.status {
+            Some(429) | Some(503) => Some(e
+                .header("Retry-After")
+                .and_then(|x| x.trim().parse().ok())
+                .map(Duration::from_secs)),
+            _ => None,
+        },
+    }
+}
+
+impl<A: HttpClient> HttpClient for RetryClient<A> {
+    fn user_agent(&mut self, user_agent: String) {
+        self.client.user_agent(user_agent)
+    }
+
+    fn get<'a, I>(&self, base_url: &str, args: I) -> Result<String, Error>
+            where I: Iterator<Item=(&'a str, &'a str)> {
+        let args: Vec<_> = args.collect();
+        let mut backoff = self.backoff;
+        let mut retries = 0;
+        loop {
+            self.throttle();
+            let result = self.client.get(base_url, args.iter().cloned());
+            if retries >= self.max_retries {
+                return result;
+            }
+            let wait = match retry_after(&result) {
+                Some(Some(wait)) => wait,
+                Some(None) => cmp::min(backoff, self.max_backoff),
+                None => return result,
+            };
+            thread::sleep(wait);
+            backoff = backoff.checked_mul(2).unwrap_or(self.max_backoff).min(self.max_backoff);
+            retries += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{RetryClient, retry_after};
+    use super::super::{Error, HttpClient};
+    use std::sync::Mutex;
+    use std::time::{Duration, Instant};
+
+    #[derive(Default)]
+    struct FlakyClient {
+        pub arguments: Mutex<Vec<Vec<(String, String)>>>,
+        pub response: Mutex<Vec<Result<String, Error>>>,
+    }
+
+    impl HttpClient for FlakyClient {
+        fn user_agent(&mut self, _: String) {}
+
+        fn get<'a, I>(&self, _: &str, args: I) -> Result<String, Error>
+                where I: Iterator<Item=(&'a str, &'a str)> {
+            self.arguments.lock().unwrap().push(args.map(|x| (x.0.to_owned(), x.1.to_owned())).collect());
+            self.response.lock().unwrap().remove(0)
+        }
+    }
+
+    fn client() -> RetryClient<FlakyClient> {
+        RetryClient {
+            backoff: Duration::from_millis(1),
+            max_retries: 2,
+            ..RetryClient::default()
+        }
+    }
+
+    #[test]
+    fn retry_status() {
+        let client = client();
+        client.client.response.lock().unwrap().push(Err(Error::from_status(503, vec![("Retry-After".to_owned(), "0".to_owned())])));
+        client.client.response.lock().unwrap().push(Err(Error::from_status(429, vec![])));
+        client.client.response.lock().unwrap().push(Ok("hello".to_owned()));
+        assert_eq!(client.get("http://a", vec![("a", "1")].into_iter()).unwrap(), "hello".to_owned());
+        assert_eq!(*client.client.arguments.lock().unwrap(), vec![vec![("a".to_owned(), "1".to_owned())]; 3]);
+    }
+
+    #[test]
+    fn retry_maxlag() {
+        let client = client();
+        client.client.response.lock().unwrap().push(Ok("{\"error\":{\"code\":\"maxlag\",\"lag\":0}}".to_owned()));
+        client.client.response.lock().unwrap().push(Ok("hello".to_owned()));
+        assert_eq!(client.get("http://a", vec![].into_iter()).unwrap(), "hello".to_owned());
+    }
+
+    #[test]
+    fn server_wait() {
+        let maxlag = Ok("{\"error\":{\"code\":\"maxlag\",\"lag\":1.5}}".to_owned());
+        assert_eq!(retry_after(&maxlag), Some(Some(Duration::from_millis(1500))));
+        let status = Err(Error::from_status(429, vec![("Retry-After".to_owned(), "120".to_owned())]));
+        assert_eq!(retry_after(&status), Some(Some(Duration::from_secs(120))));
+        assert_eq!(retry_after(&Err(Error::from_status(503, vec![]))), Some(None));
+        assert_eq!(retry_after(&Ok("{\"error\":{\"code\":\"badvalue\"}}".to_owned())), None);
+
+        let mut client = client();
+        client.max_backoff = Duration::from_millis(1);
+        client.client.response.lock().unwrap().push(Ok("{\"error\":{\"code\":\"maxlag\",\"lag\":0.05}}".to_owned()));
+        client.client.response.lock().unwrap().push(Ok("hello".to_owned()));
+        let start = Instant::now();
+        assert_eq!(client.get("http://a", vec![].into_iter()).unwrap(), "hello".to_owned());
+        assert!(start.elapsed() >= Duration::from_millis(50));
+    }
+
+    #[test]
+    fn give_up() {
+        let client = client();
+        client.client.response.lock().unwrap().push(Err(Error::from_status(503, vec![])));
+        client.client.response.lock().unwrap().push(Err(Error::from_status(503, vec![])));
+        client.client.response.lock().unwrap().push(Err(Error::from_status(503, vec![])));
+        assert_eq!(client.get("http://a", vec![].into_iter()).unwrap_err().status, Some(503));
+        assert_eq!(client.client.arguments.lock().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn no_retry() {
+        let client = client();
+        client.client.response.lock().unwrap().push(Err(Error::from_status(404, vec![])));
+        assert_eq!(client.get("http://a", vec![].into_iter()).unwrap_err().status, Some(404));
+        assert_eq!(client.client.arguments.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn requests_per_second() {
+        let mut client = client();
+        client.requests_per_second = Some(20.0);
+        client.client.response.lock().unwrap().push(Ok("hello".to_owned()));
+        client.client.response.lock().unwrap().push(Ok("world".to_owned()));
+        let start = Instant::now();
+        client.get("http://a", vec![].into_iter()).unwrap();
+        client.get("http://a", vec![].into_iter()).unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(50));
+    }
+}
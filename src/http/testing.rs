@@ -0,0 +1,23 @@
+//! Clients shared by the tests of the `http` wrappers.
+use std::sync::Mutex;
+
+use super::{Error, HttpClient};
+
+/// Answers every request with "response N", N being the number of requests
+/// sent so far, or with `response` if set.
+#[derive(Default)]
+pub struct CountingClient {
+    pub requests: Mutex<u32>,
+    pub response: Option<String>,
+}
+
+impl HttpClient for CountingClient {
+    fn user_agent(&mut self, _: String) {}
+
+    fn get<'a, I>(&self, _: &str, _: I) -> Result<String, Error>
+            where I: Iterator<Item=(&'a str, &'a str)> {
+        let mut requests = self.requests.lock().unwrap();
+        *requests += 1;
+        Ok(self.response.clone().unwrap_or(format!("response {}", requests)))
+    }
+}
@@ -1,57 +1,206 @@
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::fmt;
+use std::mem;
+use std::str;
 use std::vec::IntoIter;
 use std::marker::PhantomData;
 
+use serde_json;
 use serde_json::Value;
 
-use super::{Page, Result, http};
+use super::{Error, Page, Result, Wikipedia, http};
 
+/// An iterator over the items of a `Page`, following `continue` parameters
+/// as needed.
+///
+/// As an `Iterator` it stops at the first error, be it a failed request or
+/// an item that cannot be parsed, which looks like the end of the items.
+/// Use `try_next` or `results` to tell them apart.
 pub struct Iter<'a, A: 'a + http::HttpClient, B: IterItem> {
-    page: &'a Page<'a, A>,
+    page: Page<'a, A>,
     inner: IntoIter<Value>,
     cont: Option<Vec<(String, String)>>,
+    /// `continue` parameters used to fetch the batch in `inner`.
+    current: Option<Vec<(String, String)>>,
+    /// Number of items already taken from `inner`.
+    offset: usize,
+    options: B::Options,
     phantom: PhantomData<B>
 }
 
 impl<'a, A: http::HttpClient, B: IterItem> Iter<'a, A, B> {
-    pub fn new(page: &'a Page<A>) -> Result<Iter<'a, A, B>> {
-        let (array, cont) = try!(B::request_next(page, &None));
+    pub fn new(page: &Page<'a, A>) -> Result<Iter<'a, A, B>> {
+        Iter::with_options(page, B::Options::default())
+    }
+
+    /// Creates an iterator whose requests use `options`, for example
+    /// `RevisionOptions` for an iterator over `Revision`s.
+    pub fn with_options(page: &Page<'a, A>, options: B::Options) -> Result<Iter<'a, A, B>> {
+        Iter::resume_with_options(page, options, &Continuation::default())
+    }
+
+    /// Creates an iterator that goes on from the position saved in
+    /// `continuation`. Only the batch that was being read is fetched again.
+    pub fn resume(page: &Page<'a, A>, continuation: &Continuation) -> Result<Iter<'a, A, B>> {
+        Iter::resume_with_options(page, B::Options::default(), continuation)
+    }
+
+    /// Like `resume`, for an iterator created with `with_options`. The
+    /// `options` are not saved in the `Continuation`.
+    pub fn resume_with_options(page: &Page<'a, A>, options: B::Options, continuation: &Continuation)
+            -> Result<Iter<'a, A, B>> {
+        let (array, cont) = try!(B::request_next(page, &options, &continuation.cont));
+        let mut inner = array.into_iter();
+        for _ in 0..continuation.offset {
+            inner.next();
+        }
         Ok(Iter {
-            page: page,
-            inner: array.into_iter(),
+            page: page.clone(),
+            inner,
             cont: cont,
+            current: continuation.cont.clone(),
+            offset: continuation.offset,
+            options,
             phantom: PhantomData,
         })
     }
 
+    /// Returns the current position, to be used with `resume`.
+    pub fn continuation(&self) -> Continuation {
+        Continuation { cont: self.current.clone(), offset: self.offset }
+    }
+
     fn fetch_next(&mut self) -> Result <()> {
         if self.cont.is_some() {
-            let (array, cont) = try!(B::request_next(self.page, &self.cont));
+            let (array, cont) = try!(B::request_next(&self.page, &self.options, &self.cont));
             self.inner = array.into_iter();
-            self.cont = cont;
+            self.current = mem::replace(&mut self.cont, cont);
+            self.offset = 0;
         }
         Ok(())
     }
+
+    /// Returns the next item, fetching the following batch when needed.
+    ///
+    /// Unlike `next`, a failed request is reported instead of ending the
+    /// iteration. The continuation parameters are kept, so calling it again
+    /// retries the request that failed.
+    ///
+    /// An item that cannot be parsed is reported as `Error::JSONPathError`,
+    /// and calling it again goes on with the following item.
+    pub fn try_next(&mut self) -> Result<Option<B>> {
+        loop {
+            match self.inner.next() {
+                Some(ref v) => {
+                    self.offset += 1;
+                    return B::from_value(v).map(Some).ok_or(Error::JSONPathError);
+                },
+                None => match self.cont {
+                    Some(_) => try!(self.fetch_next()),
+                    None => return Ok(None),
+                }
+            }
+        }
+    }
+
+    /// Converts the iterator into one that yields `Result`s, see `try_next`.
+    pub fn results(self) -> Results<'a, A, B> {
+        Results { iter: self }
+    }
 }
 
 impl<'a, A: http::HttpClient, B: IterItem> Iterator for Iter<'a, A, B> {
     type Item = B;
+    /// Returns the next item, or `None` at the end or on the first error,
+    /// see `try_next`.
     fn next(&mut self) -> Option<Self::Item> {
-        match self.inner.next() {
-            Some(ref v) => B::from_value(&v),
-            None => match self.cont {
-                Some(_) => match self.fetch_next() {
-                    Ok(_) => self.inner.next().and_then(|x| B::from_value(&x)),
-                    Err(_) => None,
-                },
-                None => None,
-            }
+        self.try_next().unwrap_or(None)
+    }
+}
+
+/// An iterator over the items of an `Iter` that reports errors.
+///
+/// After an error the next call retries the failed request, so a persistent
+/// failure yields errors indefinitely.
+pub struct Results<'a, A: 'a + http::HttpClient, B: IterItem> {
+    iter: Iter<'a, A, B>,
+}
+
+impl<'a, A: http::HttpClient, B: IterItem> Results<'a, A, B> {
+    /// Returns the underlying `Iter`.
+    pub fn into_inner(self) -> Iter<'a, A, B> {
+        self.iter
+    }
+}
+
+impl<'a, A: http::HttpClient, B: IterItem> Iterator for Results<'a, A, B> {
+    type Item = Result<B>;
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.iter.try_next() {
+            Ok(Some(b)) => Some(Ok(b)),
+            Ok(None) => None,
+            Err(e) => Some(Err(e)),
         }
     }
 }
 
+/// Position of an `Iter`, made of the `continue` parameters of the batch
+/// being read and the number of items already taken from it.
+///
+/// It can be saved as a string with `to_string` and parsed back with `parse`.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Continuation {
+    pub(crate) cont: Option<Vec<(String, String)>>,
+    pub(crate) offset: usize,
+}
+
+impl fmt::Display for Continuation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut obj = BTreeMap::new();
+        obj.insert("continue".to_owned(), match self.cont {
+            Some(ref v) => Value::Object(v
+                .iter()
+                .map(|(k, v)| (k.clone(), Value::String(v.clone())))
+                .collect()),
+            None => Value::Null,
+        });
+        obj.insert("offset".to_owned(), Value::U64(self.offset as u64));
+        let s = try!(serde_json::to_string(&Value::Object(obj)).map_err(|_| fmt::Error));
+        f.write_str(&s)
+    }
+}
+
+impl str::FromStr for Continuation {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Continuation> {
+        let value: Value = try!(serde_json::from_str(s));
+        let obj = try!(value.as_object().ok_or(Error::JSONPathError));
+        let offset = try!(obj
+            .get("offset")
+            .and_then(|x| x.as_u64())
+            .ok_or(Error::JSONPathError));
+        let cont = match obj.get("continue") {
+            None | Some(&Value::Null) => None,
+            Some(c) => Some(try!(try!(c.as_object().ok_or(Error::JSONPathError))
+                .iter()
+                .map(|(k, v)| v
+                     .as_string()
+                     .map(|v| (k.clone(), v.to_owned()))
+                     .ok_or(Error::JSONPathError))
+                .collect::<Result<Vec<_>>>())),
+        };
+        Ok(Continuation { cont, offset: offset as usize })
+    }
+}
+
 pub trait IterItem: Sized {
-    fn request_next<A: http::HttpClient>(page: &Page<A>, cont: &Option<Vec<(String, String)>>)
-            -> Result<(Vec<Value>, Option<Vec<(String, String)>>)>;
+    /// Filters or order of the requests, kept by the `Iter`. Items without
+    /// any use `()`.
+    type Options: Clone + Default;
+
+    fn request_next<A: http::HttpClient>(page: &Page<A>, options: &Self::Options,
+            cont: &Option<Vec<(String, String)>>) -> Result<(Vec<Value>, Option<Vec<(String, String)>>)>;
     fn from_value(value: &Value) -> Option<Self>;
 }
 
@@ -63,8 +212,10 @@ pub struct Image {
 }
 
 impl IterItem for Image {
-    fn request_next<A: http::HttpClient>(page: &Page<A>, cont: &Option<Vec<(String, String)>>)
-            -> Result<(Vec<Value>, Option<Vec<(String, String)>>)> {
+    type Options = ();
+
+    fn request_next<A: http::HttpClient>(page: &Page<A>, _: &Self::Options,
+            cont: &Option<Vec<(String, String)>>) -> Result<(Vec<Value>, Option<Vec<(String, String)>>)> {
         page.request_images(&cont)
     }
 
@@ -109,8 +260,10 @@ pub struct Reference {
 }
 
 impl IterItem for Reference {
-    fn request_next<A: http::HttpClient>(page: &Page<A>, cont: &Option<Vec<(String, String)>>)
-            -> Result<(Vec<Value>, Option<Vec<(String, String)>>)> {
+    type Options = ();
+
+    fn request_next<A: http::HttpClient>(page: &Page<A>, _: &Self::Options,
+            cont: &Option<Vec<(String, String)>>) -> Result<(Vec<Value>, Option<Vec<(String, String)>>)> {
         page.request_extlinks(&cont)
     }
 
@@ -135,8 +288,10 @@ pub struct Link {
 }
 
 impl IterItem for Link {
-    fn request_next<A: http::HttpClient>(page: &Page<A>, cont: &Option<Vec<(String, String)>>)
-            -> Result<(Vec<Value>, Option<Vec<(String, String)>>)> {
+    type Options = ();
+
+    fn request_next<A: http::HttpClient>(page: &Page<A>, _: &Self::Options,
+            cont: &Option<Vec<(String, String)>>) -> Result<(Vec<Value>, Option<Vec<(String, String)>>)> {
         page.request_links(&cont)
     }
 
@@ -155,8 +310,10 @@ pub struct Category {
 }
 
 impl IterItem for Category {
-    fn request_next<A: http::HttpClient>(page: &Page<A>, cont: &Option<Vec<(String, String)>>)
-            -> Result<(Vec<Value>, Option<Vec<(String, String)>>)> {
+    type Options = ();
+
+    fn request_next<A: http::HttpClient>(page: &Page<A>, _: &Self::Options,
+            cont: &Option<Vec<(String, String)>>) -> Result<(Vec<Value>, Option<Vec<(String, String)>>)> {
         page.request_categories(&cont)
     }
 
@@ -174,3 +331,399 @@ impl IterItem for Category {
             })
     }
 }
+
+/// A link to the same article in another language.
+#[derive(Debug, PartialEq)]
+pub struct LangLink {
+    /// Language code, like "es".
+    pub lang: String,
+    /// Title of the article in that language.
+    pub title: String,
+    pub url: String,
+    /// Name of the language in that language, like "español".
+    pub autonym: String,
+}
+
+impl LangLink {
+    /// Returns the linked article. `wikipedia` should be the one for
+    /// `lang`, see `Wikipedia::with_language`.
+    pub fn page<'a, A: http::HttpClient>(&self, wikipedia: &'a Wikipedia<A>) -> Page<'a, A> {
+        wikipedia.page_from_title(self.title.clone())
+    }
+}
+
+impl IterItem for LangLink {
+    type Options = ();
+
+    fn request_next<A: http::HttpClient>(page: &Page<A>, _: &Self::Options,
+            cont: &Option<Vec<(String, String)>>) -> Result<(Vec<Value>, Option<Vec<(String, String)>>)> {
+        page.request_langlinks(&cont)
+    }
+
+    fn from_value(value: &Value) -> Option<LangLink> {
+        let obj = match value.as_object() {
+            Some(o) => o,
+            None => return None,
+        };
+        let string = |field: &str| obj.get(field).and_then(|x| x.as_string()).map(|x| x.to_owned());
+
+        Some(LangLink {
+            lang: match string("lang") {
+                Some(l) => l,
+                None => return None,
+            },
+            title: match string("*").or_else(|| string("title")) {
+                Some(t) => t,
+                None => return None,
+            },
+            url: string("url").unwrap_or_default(),
+            autonym: string("autonym").unwrap_or_default(),
+        })
+    }
+}
+
+/// Which pages `Page::get_backlinks` returns according to whether they are
+/// redirects.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum RedirectFilter {
+    #[default]
+    All,
+    Redirects,
+    NonRedirects,
+}
+
+/// Filters of `Page::get_backlinks`.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct BacklinkOptions {
+    /// Only include pages in these namespaces, all if empty.
+    pub namespaces: Vec<i64>,
+    pub filter: RedirectFilter,
+    /// Also include the pages linking to the redirects to the page.
+    pub follow_redirects: bool,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Backlink {
+    pub pageid: u64,
+    pub ns: i64,
+    pub title: String,
+    /// Whether the linking page is a redirect to the page.
+    pub redirect: bool,
+    /// Title of the redirect this page links through, if it does not link
+    /// directly. Only set when following redirects.
+    pub via: Option<String>,
+}
+
+impl IterItem for Backlink {
+    type Options = BacklinkOptions;
+
+    fn request_next<A: http::HttpClient>(page: &Page<A>, options: &Self::Options,
+            cont: &Option<Vec<(String, String)>>) -> Result<(Vec<Value>, Option<Vec<(String, String)>>)> {
+        page.request_backlinks(options, &cont)
+    }
+
+    fn from_value(value: &Value) -> Option<Backlink> {
+        let obj = match value.as_object() {
+            Some(o) => o,
+            None => return None,
+        };
+
+        Some(Backlink {
+            pageid: obj.get("pageid").and_then(|x| x.as_u64()).unwrap_or(0),
+            ns: obj.get("ns").and_then(|x| x.as_i64()).unwrap_or(0),
+            title: match obj.get("title").and_then(|x| x.as_string()) {
+                Some(t) => t.to_owned(),
+                None => return None,
+            },
+            redirect: obj.contains_key("redirect"),
+            via: obj.get("via").and_then(|x| x.as_string()).map(|x| x.to_owned()),
+        })
+    }
+}
+
+/// Kind of page in a category.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MemberType {
+    Page,
+    Subcat,
+    File,
+}
+
+impl MemberType {
+    fn as_str(&self) -> &'static str {
+        match *self {
+            MemberType::Page => "page",
+            MemberType::Subcat => "subcat",
+            MemberType::File => "file",
+        }
+    }
+}
+
+/// Order of the members returned by `Page::get_category_members`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum CategorySort {
+    /// Alphabetically by sort key, the order shown in the category page.
+    #[default]
+    Sortkey,
+    /// By the time each page was added to the category.
+    Timestamp,
+}
+
+/// Filters and order of `Page::get_category_members`.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct CategoryMemberOptions {
+    /// Only include members of these types, all if empty.
+    pub types: Vec<MemberType>,
+    /// Only include pages in these namespaces, all if empty.
+    pub namespaces: Vec<i64>,
+    pub sort: CategorySort,
+    /// Sort in descending order.
+    pub descending: bool,
+    /// Where to start: a timestamp like "2016-01-01T00:00:00Z" when sorting
+    /// by `Timestamp`, a sort key prefix when sorting by `Sortkey`.
+    pub start: Option<String>,
+    /// Where to stop, like `start`.
+    pub end: Option<String>,
+}
+
+impl CategoryMemberOptions {
+    pub(crate) fn params(&self) -> Vec<(String, String)> {
+        let join = |v: Vec<String>| v.join("|");
+        let mut params = vec![
+            ("cmsort".to_owned(), match self.sort {
+                CategorySort::Sortkey => "sortkey",
+                CategorySort::Timestamp => "timestamp",
+            }.to_owned()),
+            ("cmdir".to_owned(), if self.descending { "desc" } else { "asc" }.to_owned()),
+        ];
+        if !self.types.is_empty() {
+            params.push(("cmtype".to_owned(),
+                join(self.types.iter().map(|t| t.as_str().to_owned()).collect())));
+        }
+        if !self.namespaces.is_empty() {
+            params.push(("cmnamespace".to_owned(),
+                join(self.namespaces.iter().map(|n| n.to_string()).collect())));
+        }
+        let (start, end) = match self.sort {
+            CategorySort::Sortkey => ("cmstartsortkeyprefix", "cmendsortkeyprefix"),
+            CategorySort::Timestamp => ("cmstart", "cmend"),
+        };
+        if let Some(ref s) = self.start {
+            params.push((start.to_owned(), s.clone()));
+        }
+        if let Some(ref e) = self.end {
+            params.push((end.to_owned(), e.clone()));
+        }
+        params
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct CategoryMember {
+    pub pageid: u64,
+    pub ns: i64,
+    pub title: String,
+    pub member_type: MemberType,
+    /// When the page was added to the category.
+    pub timestamp: String,
+}
+
+impl IterItem for CategoryMember {
+    type Options = CategoryMemberOptions;
+
+    fn request_next<A: http::HttpClient>(page: &Page<A>, options: &Self::Options,
+            cont: &Option<Vec<(String, String)>>) -> Result<(Vec<Value>, Option<Vec<(String, String)>>)> {
+        page.request_category_members(options, &cont)
+    }
+
+    fn from_value(value: &Value) -> Option<CategoryMember> {
+        let obj = match value.as_object() {
+            Some(o) => o,
+            None => return None,
+        };
+
+        Some(CategoryMember {
+            pageid: obj.get("pageid").and_then(|x| x.as_u64()).unwrap_or(0),
+            ns: obj.get("ns").and_then(|x| x.as_i64()).unwrap_or(0),
+            title: match obj.get("title").and_then(|x| x.as_string()) {
+                Some(t) => t.to_owned(),
+                None => return None,
+            },
+            member_type: match obj.get("type").and_then(|x| x.as_string()) {
+                Some("subcat") => MemberType::Subcat,
+                Some("file") => MemberType::File,
+                _ => MemberType::Page,
+            },
+            timestamp: obj
+                .get("timestamp")
+                .and_then(|x| x.as_string())
+                .unwrap_or("")
+                .to_owned(),
+        })
+    }
+}
+
+/// Breadth-first traversal of a category and its subcategories, see
+/// `Wikipedia::category_tree`.
+///
+/// Yields every page or file once, along with the titles of the categories
+/// leading to it from the root, both included. Categories already visited
+/// are skipped, so cycles end the walk. After an error the next call
+/// retries the failed request.
+pub struct CategoryTree<'a, A: 'a + http::HttpClient> {
+    wikipedia: &'a Wikipedia<A>,
+    max_depth: usize,
+    /// Paths to the categories still to list, the last title being the
+    /// category.
+    queue: VecDeque<Vec<String>>,
+    current: Option<(Iter<'a, A, CategoryMember>, Vec<String>)>,
+    categories: HashSet<String>,
+    pages: HashSet<String>,
+}
+
+impl<'a, A: http::HttpClient> CategoryTree<'a, A> {
+    /// Creates a traversal of the category titled `title` going down
+    /// `max_depth` levels of subcategories, 0 to only list `title`.
+    pub fn new(wikipedia: &'a Wikipedia<A>, title: String, max_depth: usize) -> Self {
+        let mut categories = HashSet::new();
+        categories.insert(title.clone());
+        CategoryTree {
+            wikipedia,
+            max_depth,
+            queue: vec![vec![title]].into_iter().collect(),
+            current: None,
+            categories,
+            pages: HashSet::new(),
+        }
+    }
+
+    /// Returns the next page and its path, fetching more members when
+    /// needed.
+    pub fn try_next(&mut self) -> Result<Option<(CategoryMember, Vec<String>)>> {
+        loop {
+            if self.current.is_none() {
+                let path = match self.queue.front() {
+                    Some(p) => p.clone(),
+                    None => return Ok(None),
+                };
+                let page = self.wikipedia.page_from_title(path[path.len() - 1].clone());
+                let iter = try!(page.get_category_members(&CategoryMemberOptions::default()));
+                self.queue.pop_front();
+                self.current = Some((iter, path));
+            }
+            let (member, path) = match self.current {
+                Some((ref mut iter, ref path)) => (try!(iter.try_next()), path.clone()),
+                None => continue,
+            };
+            let member = match member {
+                Some(m) => m,
+                None => {
+                    self.current = None;
+                    continue;
+                },
+            };
+            if member.member_type == MemberType::Subcat {
+                if path.len() <= self.max_depth && self.categories.insert(member.title.clone()) {
+                    let mut subpath = path;
+                    subpath.push(member.title);
+                    self.queue.push_back(subpath);
+                }
+            } else if self.pages.insert(member.title.clone()) {
+                return Ok(Some((member, path)));
+            }
+        }
+    }
+}
+
+impl<'a, A: http::HttpClient> Iterator for CategoryTree<'a, A> {
+    type Item = Result<(CategoryMember, Vec<String>)>;
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.try_next() {
+            Ok(Some(p)) => Some(Ok(p)),
+            Ok(None) => None,
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+/// Order of the revisions returned by `Page::get_revisions`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Direction {
+    /// Newest first.
+    #[default]
+    Older,
+    /// Oldest first.
+    Newer,
+}
+
+/// Order and filters of `Page::get_revisions`.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct RevisionOptions {
+    pub direction: Direction,
+    /// Timestamp to start from, like "2016-01-01T00:00:00Z".
+    pub start: Option<String>,
+    /// Timestamp to stop at.
+    pub end: Option<String>,
+    /// Only include revisions made by this user.
+    pub user: Option<String>,
+    /// Exclude revisions made by this user.
+    pub exclude_user: Option<String>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Revision {
+    pub revid: u64,
+    /// Id of the previous revision, 0 for the first one.
+    pub parentid: u64,
+    pub timestamp: String,
+    /// Empty if the user was hidden.
+    pub user: String,
+    pub comment: String,
+    /// Size of the revision, in bytes.
+    pub size: u64,
+    pub minor: bool,
+    pub tags: Vec<String>,
+    /// SHA-1 of the revision's content.
+    pub sha1: String,
+}
+
+impl IterItem for Revision {
+    type Options = RevisionOptions;
+
+    fn request_next<A: http::HttpClient>(page: &Page<A>, options: &Self::Options,
+            cont: &Option<Vec<(String, String)>>) -> Result<(Vec<Value>, Option<Vec<(String, String)>>)> {
+        page.request_revisions(options, &cont)
+    }
+
+    fn from_value(value: &Value) -> Option<Revision> {
+        let obj = match value.as_object() {
+            Some(o) => o,
+            None => return None,
+        };
+        let string = |field: &str| obj
+            .get(field)
+            .and_then(|x| x.as_string())
+            .unwrap_or("")
+            .to_owned();
+        let number = |field: &str| obj.get(field).and_then(|x| x.as_u64()).unwrap_or(0);
+
+        Some(Revision {
+            revid: match obj.get("revid").and_then(|x| x.as_u64()) {
+                Some(r) => r,
+                None => return None,
+            },
+            parentid: number("parentid"),
+            timestamp: string("timestamp"),
+            user: string("user"),
+            comment: string("comment"),
+            size: number("size"),
+            minor: obj.contains_key("minor"),
+            tags: obj
+                .get("tags")
+                .and_then(|x| x.as_array())
+                .map(|x| x.iter().filter_map(|t| t.as_string()).map(|t| t.to_owned()).collect())
+                .unwrap_or_default(),
+            sha1: string("sha1"),
+        })
+    }
+}
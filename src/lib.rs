@@ -11,70 +11,35 @@
 //! ```
 #[cfg(feature="http-client")] extern crate hyper;
 #[cfg(feature="http-client")] extern crate url;
+#[cfg(feature="async")] extern crate futures;
 extern crate serde_json;
 
+use std::cmp;
 use std::cmp::PartialEq;
+use std::collections::BTreeMap;
+use std::error;
+use std::fmt;
 use std::io;
 use std::result;
 
+pub mod document;
+pub mod html;
 pub mod iter;
 pub mod http;
+pub mod table;
+pub mod wikitext;
+#[cfg(feature="async")] pub mod asynchronous;
+pub use document::Document;
 pub use iter::Iter;
 
 const LANGUAGE_URL_MARKER:&'static str = "{language}";
-
-macro_rules! results {
-    ($data: expr, $query_field: expr) => {
-        // There has to be a better way to write the following code
-        try!(
-            $data.as_object()
-            .and_then(|x| x.get("query"))
-            .and_then(|x| x.as_object())
-            .and_then(|x| x.get($query_field))
-            .and_then(|x| x.as_array())
-            .ok_or(Error::JSONPathError)
-            ).into_iter().filter_map(|i|
-                i.as_object()
-                .and_then(|i| i.get("title"))
-                .and_then(|s| s.as_string().map(|s| s.to_owned()))
-                ).collect()
-    }
-}
-
-macro_rules! cont {
-    ($this: expr, $cont: expr, $($params: expr),*) => {{
-        let qp = $this.identifier.query_param();
-        let mut params = vec![
-            $($params),*,
-            ("format", "json"),
-            ("action", "query"),
-            (&*qp.0, &*qp.1),
-        ];
-        match *$cont {
-            Some(ref v) => {
-                for x in v.iter() { params.push((&*x.0, &*x.1)); }
-            },
-            None => params.push(("continue", "")),
-        }
-        let q = try!($this.wikipedia.query(params.into_iter()));
-
-        let pages = try!(q
-            .as_object()
-            .and_then(|x| x.get("query"))
-            .and_then(|x| x.as_object())
-            .and_then(|x| x.get("pages"))
-            .and_then(|x| x.as_object())
-            .ok_or(Error::JSONPathError));
-
-        Ok((pages.values().cloned().collect(), try!($this.parse_cont(&q))))
-    }}
-}
+const USER_AGENT: &str = "wikipedia (https://github.com/seppo0010/wikipedia-rs)";
 
 /// Wikipedia failed to fetch some information
 #[derive(Debug)]
 pub enum Error {
     /// Some error communicating with the server
-    HTTPError,
+    HTTPError(http::Error),
     /// Error reading response
     IOError(io::Error),
     /// Failed to parse JSON response
@@ -83,11 +48,48 @@ pub enum Error {
     JSONPathError,
     /// One of the parameters provided (identified by `String`) is invalid
     InvalidParameter(String),
+    /// The API refused the request, see
+    /// https://www.mediawiki.org/wiki/API:Errors_and_warnings
+    ApiError {
+        /// Machine readable error code, for example "missingtitle"
+        code: String,
+        /// Human readable description of the error
+        info: String,
+    },
+    /// The page is a disambiguation page, listing the titles it links to.
+    /// Only returned when `Wikipedia::disambiguation_errors` is set.
+    Disambiguation(Vec<String>),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::HTTPError(ref e) => write!(f, "{}", e),
+            Error::IOError(ref e) => write!(f, "error reading response: {}", e),
+            Error::JSONError(ref e) => write!(f, "invalid JSON response: {}", e),
+            Error::JSONPathError => write!(f, "unexpected JSON response"),
+            Error::InvalidParameter(ref p) => write!(f, "invalid parameter {}", p),
+            Error::ApiError { ref code, ref info } => write!(f, "API error {}: {}", code, info),
+            Error::Disambiguation(ref titles) =>
+                write!(f, "disambiguation page, may refer to: {}", titles.join(", ")),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            Error::HTTPError(ref e) => Some(e),
+            Error::IOError(ref e) => Some(e),
+            Error::JSONError(ref e) => Some(e),
+            _ => None,
+        }
+    }
 }
 
 impl std::convert::From<http::Error> for Error {
-    fn from(_: http::Error) -> Self {
-        Error::HTTPError
+    fn from(e: http::Error) -> Self {
+        Error::HTTPError(e)
     }
 }
 
@@ -106,7 +108,7 @@ impl std::convert::From<serde_json::error::Error> for Error {
 pub type Result<T> = result::Result<T, Error>;
 
 #[derive(Debug)]
-pub struct Wikipedia<A: http::HttpClient> {
+pub struct Wikipedia<A> {
     /// HttpClient struct.
     pub client: A,
     /// Url is created by concatenating `pre_language_url` + `language` + `post_language_url`.
@@ -121,8 +123,20 @@ pub struct Wikipedia<A: http::HttpClient> {
     pub images_results: String,
     /// Like `images_results`, for links and references.
     pub links_results: String,
-    /// Like `images_results`, for categories.
+    /// Like `images_results`, for categories and category members.
     pub categories_results: String,
+    /// Like `images_results`, for revisions.
+    pub revisions_results: String,
+    /// If set, sent as the `maxlag` parameter of every request, so that the
+    /// servers refuse it when replication lag is higher than this number of
+    /// seconds. See `http::RetryClient` to retry those requests.
+    pub maxlag: Option<u32>,
+    /// Number of titles to send in each request when calling `pages`.
+    pub titles_per_request: usize,
+    /// If true, `get_content` and `get_summary` of `Page` and `AsyncPage`
+    /// return `Error::Disambiguation` for disambiguation pages instead of
+    /// their text. Listing the candidate titles takes further requests.
+    pub disambiguation_errors: bool,
 }
 
 impl<A: http::HttpClient + Default> Default for Wikipedia<A> {
@@ -131,10 +145,10 @@ impl<A: http::HttpClient + Default> Default for Wikipedia<A> {
     }
 }
 
-impl<A: http::HttpClient> Wikipedia<A> {
-    /// Creates a new object using the provided client and default values.
-    pub fn new(mut client: A) -> Self {
-        client.user_agent("wikipedia (https://github.com/seppo0010/wikipedia-rs)".to_owned());
+impl<A> Wikipedia<A> {
+    /// Creates a new object using the provided client, which already has
+    /// its user agent set, and default values.
+    fn with_client(client: A) -> Self {
         Wikipedia {
             client: client,
             pre_language_url: "https://".to_owned(),
@@ -144,49 +158,13 @@ impl<A: http::HttpClient> Wikipedia<A> {
             images_results: "max".to_owned(),
             links_results: "max".to_owned(),
             categories_results: "max".to_owned(),
+            revisions_results: "max".to_owned(),
+            maxlag: None,
+            titles_per_request: 50,
+            disambiguation_errors: false,
         }
     }
 
-    /// Returns a list of languages in the form of (`identifier`, `language`),
-    /// for example [("en", "English"), ("es", "Español")]
-    pub fn get_languages(&self) -> Result<Vec<(String, String)>> {
-        let q = try!(self.query(vec![
-                ("meta", "siteinfo"),
-                ("siprop", "languages"),
-                ("format", "json"),
-                ("action", "query"),
-            ].into_iter()));
-
-        Ok(try!(q
-            .as_object()
-            .and_then(|x| x.get("query"))
-            .and_then(|x| x.as_object())
-            .and_then(|x| x.get("languages"))
-            .and_then(|x| x.as_array())
-            .ok_or(Error::JSONPathError))
-            .into_iter()
-            .filter_map(|x| {
-                        let o = x.as_object();
-                        Some((
-                            match o
-                                .and_then(|x| x.get("code"))
-                                .and_then(|x| x.as_string())
-                                .map(|x| x.to_owned()) {
-                                    Some(v) => v,
-                                    None => return None,
-                                },
-                            match o
-                                .and_then(|x| x.get("*"))
-                                .and_then(|x| x.as_string())
-                                .map(|x| x.to_owned()) {
-                                    Some(v) => v,
-                                    None => return None,
-                                },
-                        ))
-                    })
-            .collect())
-    }
-
     /// Returns the api url
     pub fn base_url(&self) -> String {
         format!("{}{}{}", self.pre_language_url, self.language, self.post_language_url)
@@ -195,23 +173,105 @@ impl<A: http::HttpClient> Wikipedia<A> {
     /// Updates the url format. The substring `{language}` will be replaced
     /// with the selected language.
     pub fn set_base_url(&mut self, base_url: &str) {
-        let index = match base_url.find(LANGUAGE_URL_MARKER) {
-            Some(i) => i,
+        match split_base_url(base_url) {
+            Some((pre, post)) => {
+                self.pre_language_url = pre;
+                self.post_language_url = post;
+            },
             None => {
                 self.pre_language_url = base_url.to_owned();
                 self.language = "".to_owned();
                 self.post_language_url = "".to_owned();
-                return;
-            }
-        };
-        self.pre_language_url = base_url[0..index].to_owned();
-        self.post_language_url = base_url[index+LANGUAGE_URL_MARKER.len()..].to_owned();
+            },
+        }
+    }
+
+    /// Returns the `maxlag` parameter to add to every request, if any.
+    fn maxlag_param(&self) -> Option<(String, String)> {
+        self.maxlag.map(|m| ("maxlag".to_owned(), format!("{}", m)))
+    }
+
+    fn images_query(&self) -> ContQuery {
+        ContQuery {
+            params: vec![
+                ("generator", "images"),
+                ("gimlimit", &*self.images_results),
+                ("prop", "imageinfo"),
+                ("iiprop", "url"),
+            ],
+            field: None,
+        }
+    }
+
+    fn extlinks_query(&self) -> ContQuery {
+        ContQuery {
+            params: vec![("prop", "extlinks"), ("ellimit", &*self.links_results)],
+            field: Some("extlinks"),
+        }
+    }
+
+    fn links_query(&self) -> ContQuery {
+        ContQuery {
+            params: vec![("prop", "links"), ("plnamespace", "0"), ("ellimit", &*self.links_results)],
+            field: Some("links"),
+        }
+    }
+
+    fn langlinks_query(&self) -> ContQuery {
+        ContQuery {
+            params: vec![("prop", "langlinks"), ("llprop", "url|autonym"), ("lllimit", &*self.links_results)],
+            field: Some("langlinks"),
+        }
+    }
+
+    fn categories_query(&self) -> ContQuery {
+        ContQuery {
+            params: vec![("prop", "categories"), ("cllimit", &*self.categories_results)],
+            field: Some("categories"),
+        }
+    }
+
+    fn disambiguation_options_query(&self) -> ContQuery {
+        ContQuery {
+            params: vec![
+                ("generator", "links"),
+                ("gplnamespace", "0"),
+                ("gpllimit", &*self.links_results),
+                ("prop", "description"),
+                ("redirects", ""),
+            ],
+            field: None,
+        }
+    }
+}
+
+impl<A: http::HttpClient> Wikipedia<A> {
+    /// Creates a new object using the provided client and default values.
+    pub fn new(mut client: A) -> Self {
+        client.user_agent(USER_AGENT.to_owned());
+        Wikipedia::with_client(client)
+    }
+
+    /// Returns a list of languages in the form of (`identifier`, `language`),
+    /// for example [("en", "English"), ("es", "Español")]
+    pub fn get_languages(&self) -> Result<Vec<(String, String)>> {
+        let q = try!(self.query(LANGUAGES_PARAMS.iter().cloned()));
+        parse_languages(&q)
     }
 
     fn query<'a, I>(&self, args: I) -> Result<serde_json::Value>
             where I: Iterator<Item=(&'a str, &'a str)> {
-        let response_str = try!(self.client.get(&*self.base_url(), args));
-        Ok(try!(serde_json::from_str(&*response_str)))
+        let maxlag = self.maxlag_param();
+        let mut args: Vec<(&str, &str)> = args.collect();
+        if let Some((ref k, ref v)) = maxlag {
+            args.push((k, v));
+        }
+        let response_str = try!(self.client.get(&self.base_url(), args.into_iter()));
+        parse_response(&response_str)
+    }
+
+    fn query_params(&self, params: &[(String, String)]) -> Result<serde_json::Value> {
+        self.query(params.iter().map(|(k, v)| (&**k, &**v)))
     }
 
     /// Searches for a string and returns a list of relevant page titles.
@@ -226,17 +286,8 @@ impl<A: http::HttpClient> Wikipedia<A> {
     /// assert!(results.contains(&"Computer keyboard".to_owned()));
     /// ```
     pub fn search(&self, query: &str) -> Result<Vec<String>> {
-        let results = &*format!("{}", self.search_results);
-        let data = try!(self.query(vec![
-                ("list", "search"),
-                ("srprop", ""),
-                ("srlimit", results),
-                ("srsearch", query),
-                ("format", "json"),
-                ("action", "query"),
-            ].into_iter()));
-
-        Ok(results!(data, "search"))
+        let data = try!(self.query_params(&search_params(self.search_results, query)));
+        parse_results(&data, "search")
     }
 
     /// Search articles within `radius` meters of `latitude` and `longitude`.
@@ -251,38 +302,15 @@ impl<A: http::HttpClient> Wikipedia<A> {
     /// assert!(results.contains(&"Madison Square Garden".to_owned()));
     /// ```
     pub fn geosearch(&self, latitude: f64, longitude: f64, radius: u16) -> Result<Vec<String>> {
-        if latitude < -90.0 || latitude > 90.0 {
-            return Err(Error::InvalidParameter("latitude".to_string()))
-        }
-        if longitude < -180.0 || longitude > 180.0 {
-            return Err(Error::InvalidParameter("longitude".to_string()))
-        }
-        if radius < 10 || radius > 10000 {
-            return Err(Error::InvalidParameter("radius".to_string()))
-        }
-        let results = &*format!("{}", self.search_results);
-        let data = try!(self.query(vec![
-                ("list", "geosearch"),
-                ("gsradius", &*format!("{}", radius)),
-                ("gscoord", &*format!("{}|{}", latitude, longitude)),
-                ("gslimit", results),
-                ("format", "json"),
-                ("action", "query"),
-                ].into_iter()));
-        Ok(results!(data, "geosearch"))
+        let params = try!(geosearch_params(self.search_results, latitude, longitude, radius));
+        let data = try!(self.query_params(&params));
+        parse_results(&data, "geosearch")
     }
 
     /// Fetches `count` random articles' title.
     pub fn random_count(&self, count: u8) -> Result<Vec<String>> {
-        let data = try!(self.query(vec![
-                ("list", "random"),
-                ("rnnamespace", "0"),
-                ("rnlimit", &*format!("{}", count)),
-                ("format", "json"),
-                ("action", "query"),
-                ].into_iter()));
-        let r:Vec<String> = results!(data, "random");
-        Ok(r)
+        let data = try!(self.query_params(&random_params(count)));
+        parse_results(&data, "random")
     }
 
     /// Fetches a random article's title.
@@ -299,9 +327,335 @@ impl<A: http::HttpClient> Wikipedia<A> {
     pub fn page_from_pageid<'a>(&'a self, pageid: String) -> Page<'a, A> {
         Page::from_pageid(self, pageid)
     }
+
+    /// Creates an iterator over the pages in the category `name`, with or
+    /// without the "Category:" prefix.
+    pub fn category_members<'a>(&'a self, name: &str, options: &iter::CategoryMemberOptions)
+            -> Result<Iter<'a, A, iter::CategoryMember>> {
+        self.page_from_title(category_title(name)).get_category_members(options)
+    }
+
+    /// Walks the category `name` and its subcategories, up to `max_depth`
+    /// levels deep, yielding each page once with the categories leading to
+    /// it. See `iter::CategoryTree`.
+    pub fn category_tree<'a>(&'a self, name: &str, max_depth: usize) -> iter::CategoryTree<'a, A> {
+        iter::CategoryTree::new(self, category_title(name), max_depth)
+    }
+
+    /// Fetches the `pageid`, summary and coordinates of many pages, sending
+    /// `titles_per_request` titles in each request.
+    ///
+    /// The result is keyed by the normalized form of each title, and includes
+    /// missing and invalid titles as well.
+    pub fn pages(&self, titles: &[&str]) -> Result<BTreeMap<String, BatchPage>> {
+        self.batch(titles, true)
+    }
+
+    /// Like `pages`, but fetches the content of each page instead of its
+    /// summary. The API returns a single full extract per request, so this
+    /// needs at least one request for every page.
+    pub fn pages_content(&self, titles: &[&str]) -> Result<BTreeMap<String, BatchPage>> {
+        self.batch(titles, false)
+    }
+
+    fn batch(&self, titles: &[&str], intro: bool) -> Result<BTreeMap<String, BatchPage>> {
+        let mut pages = BTreeMap::new();
+        for chunk in titles.chunks(cmp::max(self.titles_per_request, 1)) {
+            let joined = chunk.join("|");
+            let mut params = owned_params(&[
+                ("prop", "extracts|coordinates"),
+                ("explaintext", ""),
+                ("exlimit", "max"),
+                ("colimit", "max"),
+                ("redirects", ""),
+            ]);
+            if intro {
+                params.push(("exintro".to_owned(), "".to_owned()));
+            }
+            params.extend(owned_params(&[
+                ("format", "json"),
+                ("action", "query"),
+                ("titles", &*joined),
+            ]));
+
+            let mut batch = Batch::default();
+            let mut cont: Option<Vec<(String, String)>> = None;
+            loop {
+                let mut p = params.clone();
+                match cont {
+                    Some(ref v) => p.extend(v.iter().cloned()),
+                    None => p.push(("continue".to_owned(), "".to_owned())),
+                }
+                let q = try!(self.query_params(&p));
+                try!(batch.add(&q, intro));
+                cont = try!(parse_cont(&q));
+                if cont.is_none() {
+                    break;
+                }
+            }
+            for title in chunk {
+                let (key, page) = batch.resolve(title);
+                pages.insert(key, page);
+            }
+        }
+        Ok(pages)
+    }
 }
 
-#[derive(Debug)]
+impl<A: http::HttpClient + Clone> Wikipedia<A> {
+    /// Returns a copy of this object for another language, keeping the
+    /// rest of the configuration.
+    ///
+    /// The client is cloned with `Clone::clone`, so for most clients the
+    /// copy has its own cache and rate limits. To share them across
+    /// languages create this object with the client wrapped in an
+    /// `std::sync::Arc`, for example `Wikipedia::new(Arc::new(client))`;
+    /// cloning it then only clones the pointer.
+    ///
+    /// The language is not checked, see `for_language`, and it has no
+    /// effect if the url set with `set_base_url` has no `{language}`.
+    pub fn with_language(&self, language: &str) -> Wikipedia<A> {
+        Wikipedia {
+            client: self.client.clone(),
+            pre_language_url: self.pre_language_url.clone(),
+            post_language_url: self.post_language_url.clone(),
+            language: language.to_owned(),
+            search_results: self.search_results,
+            images_results: self.images_results.clone(),
+            links_results: self.links_results.clone(),
+            categories_results: self.categories_results.clone(),
+            revisions_results: self.revisions_results.clone(),
+            maxlag: self.maxlag,
+            titles_per_request: self.titles_per_request,
+            disambiguation_errors: self.disambiguation_errors,
+        }
+    }
+
+    /// Like `with_language`, but fails with `Error::InvalidParameter` if
+    /// `code` is not one of the languages returned by `get_languages`.
+    ///
+    /// This fetches the list of languages on every call, see
+    /// `for_language_in` to reuse it.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// extern crate wikipedia;
+    ///
+    /// use std::sync::Arc;
+    ///
+    /// let client = wikipedia::http::hyper::Client::default();
+    /// let wiki = wikipedia::Wikipedia::new(Arc::new(client));
+    /// let es = wiki.for_language("es").unwrap();
+    /// let page = es.page_from_title("Mundo".to_owned());
+    /// ```
+    pub fn for_language(&self, code: &str) -> Result<Wikipedia<A>> {
+        let languages = try!(self.get_languages());
+        self.for_language_in(code, &languages)
+    }
+
+    /// Like `for_language`, checking `code` against `languages`, as returned
+    /// by `get_languages`, instead of fetching them.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// extern crate wikipedia;
+    ///
+    /// use std::sync::Arc;
+    ///
+    /// let client = wikipedia::http::hyper::Client::default();
+    /// let wiki = wikipedia::Wikipedia::new(Arc::new(client));
+    /// let languages = wiki.get_languages().unwrap();
+    /// let wikis: Vec<_> = ["es", "fr", "de"]
+    ///     .iter()
+    ///     .map(|code| wiki.for_language_in(code, &languages).unwrap())
+    ///     .collect();
+    /// ```
+    pub fn for_language_in(&self, code: &str, languages: &[(String, String)])
+            -> Result<Wikipedia<A>> {
+        if !languages.iter().any(|l| l.0 == code) {
+            return Err(Error::InvalidParameter("language".to_owned()));
+        }
+        Ok(self.with_language(code))
+    }
+}
+
+/// A page fetched with `Wikipedia::pages`.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct BatchPage {
+    /// Title of the page, after following redirects.
+    pub title: String,
+    pub pageid: Option<String>,
+    /// Set by `Wikipedia::pages`.
+    pub summary: Option<String>,
+    /// Set by `Wikipedia::pages_content`.
+    pub content: Option<String>,
+    pub coordinates: Option<(f64, f64)>,
+    /// The page does not exist.
+    pub missing: bool,
+    /// The title is not valid, for example because it is empty.
+    pub invalid: bool,
+}
+
+/// Responses to a `Wikipedia::pages` request, merged across continuations.
+#[derive(Default)]
+struct Batch {
+    normalized: BTreeMap<String, String>,
+    redirects: BTreeMap<String, String>,
+    pages: BTreeMap<String, BatchPage>,
+}
+
+impl Batch {
+    fn add(&mut self, q: &serde_json::Value, intro: bool) -> Result<()> {
+        let query = try!(q
+            .as_object()
+            .and_then(|x| x.get("query"))
+            .and_then(|x| x.as_object())
+            .ok_or(Error::JSONPathError));
+        add_title_mappings(&mut self.normalized, query.get("normalized"));
+        add_title_mappings(&mut self.redirects, query.get("redirects"));
+        for p in query.get("pages").and_then(|x| x.as_object()).into_iter().flat_map(|x| x.values()) {
+            let p = try!(p.as_object().ok_or(Error::JSONPathError));
+            let title = try!(p.get("title").and_then(|x| x.as_string()).ok_or(Error::JSONPathError));
+            let page = self.pages.entry(title.to_owned()).or_insert_with(|| BatchPage {
+                title: title.to_owned(),
+                ..BatchPage::default()
+            });
+            if let Some(pageid) = p.get("pageid").and_then(|x| x.as_u64()) {
+                page.pageid = Some(format!("{}", pageid));
+            }
+            if let Some(extract) = p.get("extract").and_then(|x| x.as_string()) {
+                if intro {
+                    page.summary = Some(extract.to_owned());
+                } else {
+                    page.content = Some(extract.to_owned());
+                }
+            }
+            if let Some(c) = p
+                    .get("coordinates")
+                    .and_then(|x| x.as_array())
+                    .and_then(|x| x.iter().next())
+                    .and_then(|x| x.as_object()) {
+                if let (Some(lat), Some(lon)) = (
+                        c.get("lat").and_then(|x| x.as_f64()),
+                        c.get("lon").and_then(|x| x.as_f64())) {
+                    page.coordinates = Some((lat, lon));
+                }
+            }
+            page.missing |= p.contains_key("missing");
+            page.invalid |= p.contains_key("invalid");
+        }
+        Ok(())
+    }
+
+    /// Returns the normalized `title` and the page it leads to.
+    fn resolve(&self, title: &str) -> (String, BatchPage) {
+        let normalized = self.normalized.get(title).map(|x| &**x).unwrap_or(title);
+        let target = self.redirects.get(normalized).map(|x| &**x).unwrap_or(normalized);
+        let page = self.pages.get(target).cloned().unwrap_or_else(|| BatchPage {
+            title: target.to_owned(),
+            missing: true,
+            ..BatchPage::default()
+        });
+        (normalized.to_owned(), page)
+    }
+}
+
+/// Metadata about a page, see `Page::get_info`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PageInfo {
+    pub pageid: String,
+    /// Namespace number, 0 for articles.
+    pub ns: i64,
+    /// Canonical title, after following redirects.
+    pub title: String,
+    pub full_url: String,
+    pub edit_url: String,
+    /// Last time the page was changed or re-rendered, as an ISO 8601 timestamp.
+    pub touched: String,
+    /// Id of the latest revision.
+    pub last_rev_id: u64,
+    /// Size of the latest revision, in bytes.
+    pub length: u64,
+    /// For example "wikitext".
+    pub content_model: String,
+    /// Language code of the content.
+    pub language: String,
+    /// The page is a disambiguation page.
+    pub disambiguation: bool,
+    /// Id of the page's Wikidata item, for example "Q42".
+    pub wikibase_item: Option<String>,
+    pub protection: Vec<Protection>,
+}
+
+/// A page linked from a disambiguation page, see
+/// `Page::disambiguation_options`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DisambiguationOption {
+    pub title: String,
+    /// Short description of the page, for example "Planet in the Solar System".
+    pub description: Option<String>,
+}
+
+/// A heading of an article, see `Page::get_section_tree`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Section {
+    /// Heading text.
+    pub title: String,
+    /// Heading level, 2 for `== x ==`, 3 for `=== x ===` and so on.
+    pub level: u8,
+    /// Position in the table of contents, for example "2.1".
+    pub number: String,
+    /// Value of the `section` parameter that refers to this section. It
+    /// starts with "T-" for sections that come from a template.
+    pub index: String,
+    /// Fragment that links to the section.
+    pub anchor: String,
+    /// Offset of the heading in the page's wikitext, `None` for sections
+    /// that come from a template.
+    pub byte_offset: Option<u64>,
+    /// Sections nested under this one.
+    pub children: Vec<Section>,
+}
+
+/// A redirect followed when resolving a title, see `Page::resolve`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Redirect {
+    pub from: String,
+    pub to: String,
+    /// Section of `to` the redirect points to, for example "Bar" in "Foo#Bar".
+    pub fragment: Option<String>,
+}
+
+/// How the identifier of a `Page` leads to an article, see `Page::resolve`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Resolution {
+    /// The requested title after normalization (capitalization, underscores,
+    /// namespace aliases), if it changed.
+    pub normalized: Option<String>,
+    /// Each redirect followed, in order.
+    pub redirects: Vec<Redirect>,
+    /// Title of the final page.
+    pub title: String,
+    /// Id of the final page, or `None` if it does not exist.
+    pub pageid: Option<String>,
+    /// Section of the final page targeted by the last redirect.
+    pub fragment: Option<String>,
+}
+
+/// A restriction on who can change a page.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Protection {
+    /// Restricted action, for example "edit" or "move".
+    pub action: String,
+    /// Group allowed to perform it, for example "autoconfirmed".
+    pub level: String,
+    /// When the restriction ends, or "infinity".
+    pub expiry: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
 enum TitlePageId {
     Title(String),
     PageId(String),
@@ -314,6 +668,22 @@ impl TitlePageId {
             TitlePageId::PageId(ref s) => ("pageids".to_owned(), s.clone()),
         }
     }
+
+    /// Identifies the page in a `list` query, as `{prefix}title` or
+    /// `{prefix}pageid`.
+    fn list_param(&self, prefix: &str) -> (String, String) {
+        match *self {
+            TitlePageId::Title(ref s) => (format!("{}title", prefix), s.clone()),
+            TitlePageId::PageId(ref s) => (format!("{}pageid", prefix), s.clone()),
+        }
+    }
+
+    /// Appends the `titles` or `pageids` parameter to `params`.
+    fn with_params(&self, params: &[(&str, &str)]) -> Vec<(String, String)> {
+        let mut v = owned_params(params);
+        v.push(self.query_param());
+        v
+    }
 }
 
 #[derive(Debug)]
@@ -322,6 +692,12 @@ pub struct Page<'a, A: 'a + http::HttpClient> {
     identifier: TitlePageId,
 }
 
+impl<'a, A: http::HttpClient> Clone for Page<'a, A> {
+    fn clone(&self) -> Self {
+        Page { wikipedia: self.wikipedia, identifier: self.identifier.clone() }
+    }
+}
+
 /// A wikipedia article.
 impl<'a, A: http::HttpClient> Page<'a, A> {
     /// Creates a new `Page` given a `title`.
@@ -334,35 +710,50 @@ impl<'a, A: http::HttpClient> Page<'a, A> {
         Page { wikipedia: wikipedia, identifier: TitlePageId::PageId(pageid) }
     }
 
+    fn query(&self, params: &[(&str, &str)]) -> Result<serde_json::Value> {
+        self.wikipedia.query_params(&self.identifier.with_params(params))
+    }
+
+    /// Like `query`, but also requests the `disambiguation` page property
+    /// when `disambiguation_errors` is set, see `check_disambiguation`.
+    fn query_disambiguation(&self, params: &[(&str, &str)]) -> Result<serde_json::Value> {
+        if !self.wikipedia.disambiguation_errors {
+            return self.query(params);
+        }
+        let params = disambiguation_params(params);
+        self.query(&params.iter().map(|(k, v)| (&**k, &**v)).collect::<Vec<_>>())
+    }
+
+    /// Gets the `Page`'s metadata. Redirects are followed, the response
+    /// already has the page they lead to.
+    pub fn get_info(&self) -> Result<PageInfo> {
+        parse_info(&try!(self.query(INFO_PARAMS)))
+    }
+
+    /// Follows normalization and redirects, returning a `Page` bound to the
+    /// final `pageid` and the steps taken to reach it. Resolved `Page`s are
+    /// compared without further requests.
+    ///
+    /// If the page does not exist the returned `Page` is bound to its title.
+    pub fn resolve(&self) -> Result<(Page<'a, A>, Resolution)> {
+        let q = try!(self.query(INFO_PARAMS));
+        let requested = match self.identifier {
+            TitlePageId::Title(ref s) => &**s,
+            TitlePageId::PageId(_) => "",
+        };
+        let resolution = try!(parse_resolution(&q, requested));
+        let page = match resolution.pageid {
+            Some(ref pageid) => Page::from_pageid(self.wikipedia, pageid.clone()),
+            None => Page::from_title(self.wikipedia, resolution.title.clone()),
+        };
+        Ok((page, resolution))
+    }
+
     /// Gets the `Page`'s `pageid`.
     pub fn get_pageid(&self) -> Result<String> {
         match self.identifier {
             TitlePageId::PageId(ref s) => Ok(s.clone()),
-            TitlePageId::Title(_) => {
-                let qp = self.identifier.query_param();
-                let q = try!(self.wikipedia.query(vec![
-                    ("prop", "info|pageprops"),
-                    ("inprop", "url"),
-                    ("ppprop", "disambiguation"),
-                    ("redirects", ""),
-                    ("format", "json"),
-                    ("action", "query"),
-                    (&*qp.0, &*qp.1),
-                ].into_iter()));
-
-                match self.redirect(&q) {
-                    Some(r) => return Page::from_title(&self.wikipedia, r).get_pageid(),
-                    None => (),
-                }
-                let pages = try!(q
-                    .as_object()
-                    .and_then(|x| x.get("query"))
-                    .and_then(|x| x.as_object())
-                    .and_then(|x| x.get("pages"))
-                    .and_then(|x| x.as_object())
-                    .ok_or(Error::JSONPathError));
-                pages.keys().cloned().next().ok_or(Error::JSONPathError)
-            }
+            TitlePageId::Title(_) => self.get_info().map(|i| i.pageid),
         }
     }
 
@@ -370,394 +761,1134 @@ impl<'a, A: http::HttpClient> Page<'a, A> {
     pub fn get_title(&self) -> Result<String> {
         match self.identifier {
             TitlePageId::Title(ref s) => Ok(s.clone()),
-            TitlePageId::PageId(_) => {
-                let qp = self.identifier.query_param();
-                let q = try!(self.wikipedia.query(vec![
-                    ("prop", "info|pageprops"),
-                    ("inprop", "url"),
-                    ("ppprop", "disambiguation"),
-                    ("redirects", ""),
-                    ("format", "json"),
-                    ("action", "query"),
-                    (&*qp.0, &*qp.1),
-                ].into_iter()));
-
-                match self.redirect(&q) {
-                    Some(r) => return Ok(r),
-                    None => (),
-                }
-                let pages = try!(q
-                    .as_object()
-                    .and_then(|x| x.get("query"))
-                    .and_then(|x| x.as_object())
-                    .and_then(|x| x.get("pages"))
-                    .and_then(|x| x.as_object())
-                    .ok_or(Error::JSONPathError));
-                let page = match pages.values().next() {
-                    Some(p) => p,
-                    None => return Err(Error::JSONPathError),
-                };
-                Ok(try!(page.as_object()
-                    .and_then(|x| x.get("title"))
-                    .and_then(|x| x.as_string())
-                    .ok_or(Error::JSONPathError))
-                    .to_owned())
-            },
+            TitlePageId::PageId(_) => self.get_info().map(|i| i.title),
         }
     }
 
-    /// If the `Page` redirects to another one it returns its title, otherwise
-    /// returns None.
-    fn redirect(&self, q: &serde_json::Value) -> Option<String> {
-        q.as_object()
-            .and_then(|x| x.get("query"))
-            .and_then(|x| x.as_object())
-            .and_then(|x| x.get("redirects"))
-            .and_then(|x| x.as_array())
-            .and_then(|x| x.into_iter().next())
-            .and_then(|x| x.as_object())
-            .and_then(|x| x.get("to"))
-            .and_then(|x| x.as_string())
-            .map(|x| x.to_owned())
-    }
-
     /// Gets the markdown content of the article.
     pub fn get_content(&self) -> Result<String> {
-        let qp = self.identifier.query_param();
-        let q = try!(self.wikipedia.query(vec![
-            ("prop", "extracts|revisions"),
-            ("explaintext", ""),
-            ("rvprop", "ids"),
-            ("redirects", ""),
-            ("format", "json"),
-            ("action", "query"),
-            (&*qp.0, &*qp.1),
-        ].into_iter()));
-
-        match self.redirect(&q) {
+        let q = try!(self.query_disambiguation(CONTENT_PARAMS));
+        match redirect(&q) {
             Some(r) => return Page::from_title(&self.wikipedia, r).get_content(),
             None => (),
         }
-        let pages = try!(q
-            .as_object()
-            .and_then(|x| x.get("query"))
-            .and_then(|x| x.as_object())
-            .and_then(|x| x.get("pages"))
-            .and_then(|x| x.as_object())
-            .ok_or(Error::JSONPathError));
-        let pageid = match pages.keys().next() {
-            Some(p) => p,
-            None => return Err(Error::JSONPathError),
-        };
-        Ok(try!(pages.get(pageid)
-            .and_then(|x| x.as_object())
-            .and_then(|x| x.get("extract"))
-            .and_then(|x| x.as_string())
-            .ok_or(Error::JSONPathError))
-            .to_owned())
+        try!(self.check_disambiguation(&q));
+        parse_extract(&q)
     }
 
     /// Gets the html content of the article.
     pub fn get_html_content(&self) -> Result<String> {
-        let qp = self.identifier.query_param();
-        let q = try!(self.wikipedia.query(vec![
-            ("prop", "revisions"),
-            ("rvprop", "content"),
-            ("rvlimit", "1"),
-            ("rvparse", ""),
-            ("redirects", ""),
-            ("format", "json"),
-            ("action", "query"),
-            (&*qp.0, &*qp.1),
-        ].into_iter()));
-
-        match self.redirect(&q) {
+        let q = try!(self.query(HTML_CONTENT_PARAMS));
+        match redirect(&q) {
             Some(r) => return Page::from_title(&self.wikipedia, r).get_html_content(),
             None => (),
         }
-        let pages = try!(q
-            .as_object()
-            .and_then(|x| x.get("query"))
-            .and_then(|x| x.as_object())
-            .and_then(|x| x.get("pages"))
-            .and_then(|x| x.as_object())
-            .ok_or(Error::JSONPathError));
-        let pageid = match pages.keys().next() {
-            Some(p) => p,
-            None => return Err(Error::JSONPathError),
-        };
-        Ok(try!(pages.get(pageid)
-            .and_then(|x| x.as_object())
-            .and_then(|x| x.get("revisions"))
-            .and_then(|x| x.as_array())
-            .and_then(|x| x.into_iter().next())
-            .and_then(|x| x.as_object())
-            .and_then(|x| x.get("*"))
-            .and_then(|x| x.as_string())
-            .ok_or(Error::JSONPathError))
-            .to_owned())
+        parse_revision(&q)
+    }
+
+    /// Gets the wikitext source of the article, see `wikitext::parse`.
+    pub fn get_wikitext(&self) -> Result<String> {
+        let q = try!(self.query(WIKITEXT_PARAMS));
+        match redirect(&q) {
+            Some(r) => return Page::from_title(&self.wikipedia, r).get_wikitext(),
+            None => (),
+        }
+        parse_wikitext(&q)
+    }
+
+    /// Gets the first infobox of the article, if any.
+    pub fn get_infobox(&self) -> Result<Option<wikitext::Infobox>> {
+        self.get_wikitext().map(|w| wikitext::infobox(&wikitext::parse(&w)))
+    }
+
+    /// Gets the tables written in the article's wikitext. Tables that come
+    /// from templates are not included.
+    pub fn get_tables(&self) -> Result<Vec<table::Table>> {
+        self.get_wikitext().map(|w| table::tables(&wikitext::parse(&w)))
+    }
+
+    /// Gets the html content of the article cleaned up and converted to
+    /// Markdown, see `html::Html`.
+    pub fn get_markdown_content(&self) -> Result<String> {
+        let html = try!(self.get_html_content());
+        Ok(html::Html::parse(&html)
+            .clean()
+            .resolve_links(&self.wikipedia.base_url())
+            .to_markdown())
     }
 
     /// Gets a summary of the article.
     pub fn get_summary(&self) -> Result<String> {
-        let qp = self.identifier.query_param();
-        let q = try!(self.wikipedia.query(vec![
-            ("prop", "extracts"),
-            ("explaintext", ""),
-            ("exintro", ""),
-            ("redirects", ""),
-            ("format", "json"),
-            ("action", "query"),
-            (&*qp.0, &*qp.1),
-        ].into_iter()));
-
-        match self.redirect(&q) {
+        let q = try!(self.query_disambiguation(SUMMARY_PARAMS));
+        match redirect(&q) {
             Some(r) => return Page::from_title(&self.wikipedia, r).get_summary(),
             None => (),
         }
-        let pages = try!(q
+        try!(self.check_disambiguation(&q));
+        parse_extract(&q)
+    }
+
+    /// Returns true if the `Page` is a disambiguation page.
+    pub fn is_disambiguation(&self) -> Result<bool> {
+        self.get_info().map(|i| i.disambiguation)
+    }
+
+    /// Fetches the articles linked from the `Page`, sorted by title. For a
+    /// disambiguation page these are the candidates it may refer to.
+    pub fn disambiguation_options(&self) -> Result<Vec<DisambiguationOption>> {
+        let query = self.wikipedia.disambiguation_options_query();
+        let mut options = Vec::new();
+        let mut cont = None;
+        loop {
+            let q = try!(self.wikipedia.query_params(&query.params(&self.identifier, &cont)));
+            options.extend(parse_disambiguation_options(&q));
+            cont = try!(parse_cont(&q));
+            if cont.is_none() {
+                break;
+            }
+        }
+        Ok(sort_disambiguation_options(options))
+    }
+
+    /// Fails with `Error::Disambiguation` if `disambiguation_errors` is set
+    /// and `q`, a query that requested the `disambiguation` page property,
+    /// is about a disambiguation page.
+    fn check_disambiguation(&self, q: &serde_json::Value) -> Result<()> {
+        if !self.wikipedia.disambiguation_errors ||
+                !first_page(q).map(is_disambiguation).unwrap_or(false) {
+            return Ok(());
+        }
+        Err(disambiguation_error(try!(self.disambiguation_options())))
+    }
+
+    fn request_cont(&self, query: ContQuery, cont: &Option<Vec<(String, String)>>) ->
+            Result<(Vec<serde_json::Value>, Option<Vec<(String, String)>>)> {
+        let q = try!(self.wikipedia.query_params(&query.params(&self.identifier, cont)));
+        parse_cont_items(&q, query.field)
+    }
+
+    fn request_images(&self, cont: &Option<Vec<(String, String)>>) ->
+            Result<(Vec<serde_json::Value>, Option<Vec<(String, String)>>)> {
+        self.request_cont(self.wikipedia.images_query(), cont)
+    }
+
+    /// Creates an iterator to view all images in the `Page`.
+    pub fn get_images(&self) -> Result<Iter<A, iter::Image>> {
+        Iter::new(&self)
+    }
+
+    fn request_extlinks(&self, cont: &Option<Vec<(String, String)>>) ->
+            Result<(Vec<serde_json::Value>, Option<Vec<(String, String)>>)> {
+        self.request_cont(self.wikipedia.extlinks_query(), cont)
+    }
+
+    /// Creates an iterator to view all references (external links) in the `Page`.
+    pub fn get_references(&self) -> Result<Iter<A, iter::Reference>> {
+        Iter::new(&self)
+    }
+
+    fn request_links(&self, cont: &Option<Vec<(String, String)>>) ->
+            Result<(Vec<serde_json::Value>, Option<Vec<(String, String)>>)> {
+        self.request_cont(self.wikipedia.links_query(), cont)
+    }
+
+    /// Creates an iterator to view all internal links in the `Page`.
+    pub fn get_links(&self) -> Result<Iter<A, iter::Link>> {
+        Iter::new(&self)
+    }
+
+    fn request_langlinks(&self, cont: &Option<Vec<(String, String)>>) ->
+            Result<(Vec<serde_json::Value>, Option<Vec<(String, String)>>)> {
+        self.request_cont(self.wikipedia.langlinks_query(), cont)
+    }
+
+    /// Creates an iterator over the versions of the `Page` in other
+    /// languages. See `iter::LangLink::page` to load them.
+    pub fn get_langlinks(&self) -> Result<Iter<'a, A, iter::LangLink>> {
+        Iter::new(&self)
+    }
+
+    fn request_categories(&self, cont: &Option<Vec<(String, String)>>) ->
+            Result<(Vec<serde_json::Value>, Option<Vec<(String, String)>>)> {
+        self.request_cont(self.wikipedia.categories_query(), cont)
+    }
+
+    /// Creates an iterator to view all categories of the `Page`.
+    pub fn get_categories(&self) -> Result<Iter<A, iter::Category>> {
+        Iter::new(&self)
+    }
+
+    fn request_revisions(&self, options: &iter::RevisionOptions,
+            cont: &Option<Vec<(String, String)>>)
+            -> Result<(Vec<serde_json::Value>, Option<Vec<(String, String)>>)> {
+        let mut params = vec![
+            ("prop", "revisions"),
+            ("rvprop", "ids|timestamp|user|comment|size|flags|tags|sha1"),
+            ("rvlimit", &*self.wikipedia.revisions_results),
+            ("rvdir", match options.direction {
+                iter::Direction::Older => "older",
+                iter::Direction::Newer => "newer",
+            }),
+        ];
+        if let Some(ref start) = options.start {
+            params.push(("rvstart", start));
+        }
+        if let Some(ref end) = options.end {
+            params.push(("rvend", end));
+        }
+        if let Some(ref user) = options.user {
+            params.push(("rvuser", user));
+        }
+        if let Some(ref user) = options.exclude_user {
+            params.push(("rvexcludeuser", user));
+        }
+        let params = cont_params(&params, &self.identifier, cont);
+        let q = try!(self.wikipedia.query_params(&params));
+        parse_cont_pages(&q).map(|(pages, cont)| first_page_array(pages, cont, "revisions"))
+    }
+
+    /// Creates an iterator over the revisions of the `Page` in the order
+    /// and with the filters in `options`. By default it starts with the
+    /// newest one.
+    pub fn get_revisions(&self, options: &iter::RevisionOptions)
+            -> Result<Iter<'a, A, iter::Revision>> {
+        Iter::with_options(&self, options.clone())
+    }
+
+    fn request_backlinks(&self, options: &iter::BacklinkOptions,
+            cont: &Option<Vec<(String, String)>>)
+            -> Result<(Vec<serde_json::Value>, Option<Vec<(String, String)>>)> {
+        let namespaces = options.namespaces
+            .iter()
+            .map(|n| n.to_string())
+            .collect::<Vec<_>>()
+            .join("|");
+        let mut params = vec![
+            ("list", "backlinks"),
+            ("bllimit", &*self.wikipedia.links_results),
+            ("blfilterredir", match options.filter {
+                iter::RedirectFilter::All => "all",
+                iter::RedirectFilter::Redirects => "redirects",
+                iter::RedirectFilter::NonRedirects => "nonredirects",
+            }),
+        ];
+        if !namespaces.is_empty() {
+            params.push(("blnamespace", &*namespaces));
+        }
+        if options.follow_redirects {
+            params.push(("blredirect", ""));
+        }
+        let mut params = owned_params(&params);
+        params.push(self.identifier.list_param("bl"));
+        let q = try!(self.wikipedia.query_params(&list_params(params, cont)));
+        parse_cont_list(&q, "backlinks").map(|(links, cont)| (backlink_values(links), cont))
+    }
+
+    /// Creates an iterator over the pages linking to the `Page`, also known
+    /// as "What links here", with the filters in `options`.
+    pub fn get_backlinks(&self, options: &iter::BacklinkOptions)
+            -> Result<Iter<'a, A, iter::Backlink>> {
+        Iter::with_options(&self, options.clone())
+    }
+
+    fn request_category_members(&self, options: &iter::CategoryMemberOptions,
+            cont: &Option<Vec<(String, String)>>)
+            -> Result<(Vec<serde_json::Value>, Option<Vec<(String, String)>>)> {
+        let mut params = owned_params(&[
+            ("list", "categorymembers"),
+            ("cmprop", "ids|title|type|timestamp"),
+            ("cmlimit", &*self.wikipedia.categories_results),
+        ]);
+        params.extend(options.params());
+        params.push(self.identifier.list_param("cm"));
+        let q = try!(self.wikipedia.query_params(&list_params(params, cont)));
+        parse_cont_list(&q, "categorymembers")
+    }
+
+    /// Creates an iterator over the pages in the `Page`, which must be a
+    /// category, with the filters and order in `options`.
+    pub fn get_category_members(&self, options: &iter::CategoryMemberOptions)
+            -> Result<Iter<'a, A, iter::CategoryMember>> {
+        Iter::with_options(&self, options.clone())
+    }
+
+    /// Returns the latitude and longitude associated to the `Page` if any.
+    pub fn get_coordinates(&self) -> Result<Option<(f64, f64)>> {
+        let q = try!(self.query(COORDINATES_PARAMS));
+        match redirect(&q) {
+            Some(r) => return Page::from_title(&self.wikipedia, r).get_coordinates(),
+            None => (),
+        }
+        parse_coordinates(&q)
+    }
+
+    /// Fetches all sections of the article.
+    pub fn get_sections(&self) -> Result<Vec<String>> {
+        let pageid = try!(self.get_pageid());
+        let q = try!(self.wikipedia.query_params(&sections_params(&pageid)));
+        parse_sections(&q)
+    }
+
+    /// Fetches all sections of the article, nesting each one under the
+    /// previous section of a lower level.
+    pub fn get_section_tree(&self) -> Result<Vec<Section>> {
+        let pageid = try!(self.get_pageid());
+        let q = try!(self.wikipedia.query_params(&sections_params(&pageid)));
+        parse_section_list(&q).map(section_tree)
+    }
+
+    /// Fetches the content of the article split into sections and
+    /// paragraphs.
+    pub fn get_document(&self) -> Result<Document> {
+        self.get_content().map(|c| Document::parse(&c))
+    }
+
+    /// Fetches the plain text of the first section named `title`, at any
+    /// level, including its subsections.
+    pub fn get_section_content(&self, title: &str) -> Result<Option<String>> {
+        let content = try!(self.get_content());
+        Ok(section_content(&content, title))
+    }
+
+    /// Fetches the plain text of the section with `index`, see
+    /// `Section::index`, without its heading. Unlike `get_section_content`
+    /// it tells apart sections with the same title.
+    ///
+    /// The text is rendered from the section's wikitext with
+    /// `wikitext::plain_text`, so it may differ slightly from `get_content`.
+    /// If `subsections` is false the text stops at the first nested heading.
+    /// Returns `None` if there is no such section.
+    pub fn get_section_text(&self, index: &str, subsections: bool) -> Result<Option<String>> {
+        let wikitext = match self.get_section_wikitext(index, subsections) {
+            Ok(w) => w,
+            Err(Error::ApiError { ref code, .. }) if code == "nosuchsection" => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let body = match headings(&wikitext).first() {
+            Some(h) if h.start == 0 => &wikitext[cmp::min(h.end + 1, wikitext.len())..],
+            _ => &wikitext[..],
+        };
+        Ok(Some(wikitext_text(body)))
+    }
+
+    /// Fetches the wikitext of the section with `index`, heading included,
+    /// without downloading the rest of the article. Index "0" is the text
+    /// before the first heading.
+    ///
+    /// If `subsections` is false the text stops at the first nested heading.
+    pub fn get_section_wikitext(&self, index: &str, subsections: bool) -> Result<String> {
+        let pageid = try!(self.get_pageid());
+        let q = try!(self.wikipedia.query_params(&section_params(&pageid, index, "wikitext")));
+        let wikitext = try!(parse_field(&q, "wikitext"));
+        if subsections {
+            return Ok(wikitext);
+        }
+        Ok(match headings(&wikitext).into_iter().find(|h| h.start > 0) {
+            Some(h) => wikitext[..h.start].trim_end().to_owned(),
+            None => wikitext,
+        })
+    }
+
+    /// Fetches the html of the section with `index`, including its
+    /// subsections, without downloading the rest of the article.
+    pub fn get_section_html(&self, index: &str) -> Result<String> {
+        let pageid = try!(self.get_pageid());
+        let q = try!(self.wikipedia.query_params(&section_params(&pageid, index, "text")));
+        parse_field(&q, "text")
+    }
+}
+
+/// Two `Page`s are equal when they have the same title or `pageid` on the
+/// same wiki. Comparing them sends no requests, so aliases such as "NYC"
+/// and "New York City" are different until resolved: compare the `Page`s
+/// returned by `Page::resolve`, which are bound to the `pageid`.
+impl<'a, A: http::HttpClient> PartialEq<Page<'a, A>> for Page<'a, A> {
+    fn eq(&self, other: &Page<A>) -> bool {
+        self.identifier == other.identifier && self.wikipedia.base_url() == other.wikipedia.base_url()
+    }
+}
+
+const LANGUAGES_PARAMS: &[(&str, &str)] = &[
+    ("meta", "siteinfo"),
+    ("siprop", "languages"),
+    ("format", "json"),
+    ("action", "query"),
+];
+
+const INFO_PARAMS: &[(&str, &str)] = &[
+    ("prop", "info|pageprops"),
+    ("inprop", "url|protection"),
+    ("ppprop", "disambiguation|wikibase_item"),
+    ("redirects", ""),
+    ("format", "json"),
+    ("action", "query"),
+];
+
+const CONTENT_PARAMS: &[(&str, &str)] = &[
+    ("prop", "extracts|revisions"),
+    ("explaintext", ""),
+    ("rvprop", "ids"),
+    ("redirects", ""),
+    ("format", "json"),
+    ("action", "query"),
+];
+
+const HTML_CONTENT_PARAMS: &[(&str, &str)] = &[
+    ("prop", "revisions"),
+    ("rvprop", "content"),
+    ("rvlimit", "1"),
+    ("rvparse", ""),
+    ("redirects", ""),
+    ("format", "json"),
+    ("action", "query"),
+];
+
+const SUMMARY_PARAMS: &[(&str, &str)] = &[
+    ("prop", "extracts"),
+    ("explaintext", ""),
+    ("exintro", ""),
+    ("redirects", ""),
+    ("format", "json"),
+    ("action", "query"),
+];
+
+const WIKITEXT_PARAMS: &[(&str, &str)] = &[
+    ("prop", "revisions"),
+    ("rvprop", "content"),
+    ("rvslots", "main"),
+    ("redirects", ""),
+    ("format", "json"),
+    ("action", "query"),
+];
+
+const COORDINATES_PARAMS: &[(&str, &str)] = &[
+    ("prop", "coordinates"),
+    ("colimit", "max"),
+    ("redirects", ""),
+    ("format", "json"),
+    ("action", "query"),
+];
+
+fn owned_params(params: &[(&str, &str)]) -> Vec<(String, String)> {
+    params.iter().map(|&(k, v)| (k.to_owned(), v.to_owned())).collect()
+}
+
+/// Like `cont_params`, for `list` queries that identify the page by
+/// themselves.
+fn list_params(mut params: Vec<(String, String)>, cont: &Option<Vec<(String, String)>>)
+        -> Vec<(String, String)> {
+    params.push(("format".to_owned(), "json".to_owned()));
+    params.push(("action".to_owned(), "query".to_owned()));
+    match *cont {
+        Some(ref v) => params.extend(v.iter().cloned()),
+        None => params.push(("continue".to_owned(), "".to_owned())),
+    }
+    params
+}
+
+/// Adds the "Category:" prefix to `name` unless it already has it.
+fn category_title(name: &str) -> String {
+    if name.starts_with("Category:") {
+        name.to_owned()
+    } else {
+        format!("Category:{}", name)
+    }
+}
+
+/// Splits a base url in the parts before and after `{language}`, if present.
+fn split_base_url(base_url: &str) -> Option<(String, String)> {
+    base_url.find(LANGUAGE_URL_MARKER).map(|index| (
+        base_url[0..index].to_owned(),
+        base_url[index+LANGUAGE_URL_MARKER.len()..].to_owned(),
+    ))
+}
+
+fn search_params(search_results: u32, query: &str) -> Vec<(String, String)> {
+    owned_params(&[
+        ("list", "search"),
+        ("srprop", ""),
+        ("srlimit", &*format!("{}", search_results)),
+        ("srsearch", query),
+        ("format", "json"),
+        ("action", "query"),
+    ])
+}
+
+fn geosearch_params(search_results: u32, latitude: f64, longitude: f64, radius: u16)
+        -> Result<Vec<(String, String)>> {
+    if !(-90.0..=90.0).contains(&latitude) {
+        return Err(Error::InvalidParameter("latitude".to_string()))
+    }
+    if !(-180.0..=180.0).contains(&longitude) {
+        return Err(Error::InvalidParameter("longitude".to_string()))
+    }
+    if !(10..=10000).contains(&radius) {
+        return Err(Error::InvalidParameter("radius".to_string()))
+    }
+    Ok(owned_params(&[
+        ("list", "geosearch"),
+        ("gsradius", &*format!("{}", radius)),
+        ("gscoord", &*format!("{}|{}", latitude, longitude)),
+        ("gslimit", &*format!("{}", search_results)),
+        ("format", "json"),
+        ("action", "query"),
+    ]))
+}
+
+fn random_params(count: u8) -> Vec<(String, String)> {
+    owned_params(&[
+        ("list", "random"),
+        ("rnnamespace", "0"),
+        ("rnlimit", &*format!("{}", count)),
+        ("format", "json"),
+        ("action", "query"),
+    ])
+}
+
+fn sections_params(pageid: &str) -> Vec<(String, String)> {
+    owned_params(&[
+        ("prop", "sections"),
+        ("format", "json"),
+        ("action", "parse"),
+        ("pageid", pageid),
+    ])
+}
+
+fn section_params(pageid: &str, index: &str, prop: &str) -> Vec<(String, String)> {
+    owned_params(&[
+        ("prop", prop),
+        ("section", index),
+        ("format", "json"),
+        ("action", "parse"),
+        ("pageid", pageid),
+    ])
+}
+
+/// Builds the parameters of a query that can span several requests, adding
+/// the `continue` parameters returned by the previous one.
+fn cont_params(params: &[(&str, &str)], identifier: &TitlePageId,
+        cont: &Option<Vec<(String, String)>>) -> Vec<(String, String)> {
+    let mut params = owned_params(params);
+    params.push(("format".to_owned(), "json".to_owned()));
+    params.push(("action".to_owned(), "query".to_owned()));
+    params.push(identifier.query_param());
+    match *cont {
+        Some(ref v) => params.extend(v.iter().cloned()),
+        None => params.push(("continue".to_owned(), "".to_owned())),
+    }
+    params
+}
+
+/// A query over the items of a page that can span several requests.
+struct ContQuery<'a> {
+    params: Vec<(&'a str, &'a str)>,
+    /// Field of the page holding the items, or `None` if the items are the
+    /// pages in the response.
+    field: Option<&'static str>,
+}
+
+impl<'a> ContQuery<'a> {
+    /// Builds the parameters of the request for `identifier` after `cont`.
+    fn params(&self, identifier: &TitlePageId, cont: &Option<Vec<(String, String)>>)
+            -> Vec<(String, String)> {
+        cont_params(&self.params, identifier, cont)
+    }
+}
+
+/// Parses a response body, turning API errors into `Error::ApiError`.
+fn parse_response(response: &str) -> Result<serde_json::Value> {
+    check_api_error(try!(serde_json::from_str(response)))
+}
+
+/// Turns a `{"error": {"code": ..., "info": ...}}` response into an
+/// `Error::ApiError`.
+fn check_api_error(q: serde_json::Value) -> Result<serde_json::Value> {
+    let error = match q
             .as_object()
-            .and_then(|x| x.get("query"))
-            .and_then(|x| x.as_object())
-            .and_then(|x| x.get("pages"))
-            .and_then(|x| x.as_object())
-            .ok_or(Error::JSONPathError));
-        let pageid = match pages.keys().next() {
-            Some(p) => p,
-            None => return Err(Error::JSONPathError),
+            .and_then(|x| x.get("error"))
+            .and_then(|x| x.as_object()) {
+        Some(e) => Error::ApiError {
+            code: e.get("code").and_then(|x| x.as_string()).unwrap_or("").to_owned(),
+            info: e.get("info").and_then(|x| x.as_string()).unwrap_or("").to_owned(),
+        },
+        None => return Ok(q),
+    };
+    Err(error)
+}
+
+/// Adds the `from` and `to` titles in a list such as `normalized` or
+/// `redirects` to `map`.
+fn add_title_mappings(map: &mut BTreeMap<String, String>, list: Option<&serde_json::Value>) {
+    for x in list.and_then(|x| x.as_array()).into_iter().flatten() {
+        let from = x.as_object().and_then(|x| x.get("from")).and_then(|x| x.as_string());
+        let to = x.as_object().and_then(|x| x.get("to")).and_then(|x| x.as_string());
+        if let (Some(from), Some(to)) = (from, to) {
+            map.insert(from.to_owned(), to.to_owned());
+        }
+    }
+}
+
+/// Collects the titles found in the `query_field` list of a query response.
+fn parse_results(data: &serde_json::Value, query_field: &str) -> Result<Vec<String>> {
+    Ok(try!(data
+        .as_object()
+        .and_then(|x| x.get("query"))
+        .and_then(|x| x.as_object())
+        .and_then(|x| x.get(query_field))
+        .and_then(|x| x.as_array())
+        .ok_or(Error::JSONPathError))
+        .iter()
+        .filter_map(|i|
+            i.as_object()
+            .and_then(|i| i.get("title"))
+            .and_then(|s| s.as_string().map(|s| s.to_owned()))
+            ).collect())
+}
+
+fn parse_languages(q: &serde_json::Value) -> Result<Vec<(String, String)>> {
+    Ok(try!(q
+        .as_object()
+        .and_then(|x| x.get("query"))
+        .and_then(|x| x.as_object())
+        .and_then(|x| x.get("languages"))
+        .and_then(|x| x.as_array())
+        .ok_or(Error::JSONPathError))
+        .iter()
+        .filter_map(|x| {
+                    let o = x.as_object();
+                    Some((
+                        match o
+                            .and_then(|x| x.get("code"))
+                            .and_then(|x| x.as_string())
+                            .map(|x| x.to_owned()) {
+                                Some(v) => v,
+                                None => return None,
+                            },
+                        match o
+                            .and_then(|x| x.get("*"))
+                            .and_then(|x| x.as_string())
+                            .map(|x| x.to_owned()) {
+                                Some(v) => v,
+                                None => return None,
+                            },
+                    ))
+                })
+        .collect())
+}
+
+/// If the response redirects to another page it returns the title at the
+/// end of the redirect chain, otherwise returns None.
+fn redirect(q: &serde_json::Value) -> Option<String> {
+    redirect_chain(&parse_redirects(q)).last().map(|r| r.to.clone())
+}
+
+/// Returns the `redirects` entries of a query response, in order.
+fn parse_redirects(q: &serde_json::Value) -> Vec<Redirect> {
+    q.as_object()
+        .and_then(|x| x.get("query"))
+        .and_then(|x| x.as_object())
+        .and_then(|x| x.get("redirects"))
+        .and_then(|x| x.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|x| {
+            let o = match x.as_object() {
+                Some(o) => o,
+                None => return None,
+            };
+            let field = |f: &str| o.get(f).and_then(|x| x.as_string()).map(|x| x.to_owned());
+            field("to").map(|to| Redirect {
+                from: field("from").unwrap_or_default(),
+                to,
+                fragment: field("tofragment"),
+            })
+        })
+        .collect()
+}
+
+/// Orders `redirects` as a chain of hops, starting from the one whose source
+/// is not the target of another. Entries that are not part of that chain are
+/// ignored.
+fn redirect_chain(redirects: &[Redirect]) -> Vec<Redirect> {
+    let mut chain: Vec<Redirect> = redirects
+        .iter()
+        .find(|r| !redirects.iter().any(|x| x.to == r.from))
+        .or(redirects.first())
+        .into_iter()
+        .cloned()
+        .collect();
+    while chain.len() < redirects.len() {
+        let next = {
+            let last = &chain[chain.len() - 1].to;
+            match redirects.iter().find(|r| &r.from == last) {
+                Some(r) => r.clone(),
+                None => break,
+            }
         };
-        Ok(try!(pages.get(pageid)
-            .and_then(|x| x.as_object())
-            .and_then(|x| x.get("extract"))
-            .and_then(|x| x.as_string())
-            .ok_or(Error::JSONPathError))
-            .to_owned())
+        chain.push(next);
     }
+    chain
+}
+
+fn parse_resolution(q: &serde_json::Value, requested: &str) -> Result<Resolution> {
+    let query = try!(q
+        .as_object()
+        .and_then(|x| x.get("query"))
+        .and_then(|x| x.as_object())
+        .ok_or(Error::JSONPathError));
+    let mut normalized = BTreeMap::new();
+    add_title_mappings(&mut normalized, query.get("normalized"));
+    let normalized = normalized.get(requested).cloned();
+    let redirects = redirect_chain(&parse_redirects(q));
+    let (pageid, page) = try!(try!(parse_pages(q))
+        .iter()
+        .next()
+        .and_then(|(k, v)| v.as_object().map(|v| (k, v)))
+        .ok_or(Error::JSONPathError));
+    let title = try!(page
+        .get("title")
+        .and_then(|x| x.as_string())
+        .ok_or(Error::JSONPathError));
+    let exists = !page.contains_key("missing") && !page.contains_key("invalid");
+    Ok(Resolution {
+        normalized,
+        fragment: redirects.last().and_then(|r| r.fragment.clone()),
+        redirects,
+        title: title.to_owned(),
+        pageid: if exists { Some(pageid.clone()) } else { None },
+    })
+}
+
+fn parse_pages(q: &serde_json::Value) -> Result<&BTreeMap<String, serde_json::Value>> {
+    q.as_object()
+        .and_then(|x| x.get("query"))
+        .and_then(|x| x.as_object())
+        .and_then(|x| x.get("pages"))
+        .and_then(|x| x.as_object())
+        .ok_or(Error::JSONPathError)
+}
+
+/// Returns the first (and usually only) page in a query response.
+fn first_page(q: &serde_json::Value) -> Result<&BTreeMap<String, serde_json::Value>> {
+    try!(parse_pages(q))
+        .values()
+        .next()
+        .and_then(|x| x.as_object())
+        .ok_or(Error::JSONPathError)
+}
+
+fn parse_info(q: &serde_json::Value) -> Result<PageInfo> {
+    let (pageid, page) = try!(try!(parse_pages(q))
+        .iter()
+        .next()
+        .and_then(|(k, v)| v.as_object().map(|v| (k, v)))
+        .ok_or(Error::JSONPathError));
+    let string = |field: &str| page
+        .get(field)
+        .and_then(|x| x.as_string())
+        .unwrap_or("")
+        .to_owned();
+    let pageprops = page.get("pageprops").and_then(|x| x.as_object());
+    Ok(PageInfo {
+        pageid: pageid.clone(),
+        ns: page.get("ns").and_then(|x| x.as_i64()).unwrap_or(0),
+        title: string("title"),
+        full_url: string("fullurl"),
+        edit_url: string("editurl"),
+        touched: string("touched"),
+        last_rev_id: page.get("lastrevid").and_then(|x| x.as_u64()).unwrap_or(0),
+        length: page.get("length").and_then(|x| x.as_u64()).unwrap_or(0),
+        content_model: string("contentmodel"),
+        language: string("pagelanguage"),
+        disambiguation: is_disambiguation(page),
+        wikibase_item: pageprops
+            .and_then(|x| x.get("wikibase_item"))
+            .and_then(|x| x.as_string())
+            .map(|x| x.to_owned()),
+        protection: page
+            .get("protection")
+            .and_then(|x| x.as_array())
+            .map(|x| x.iter().filter_map(|p| {
+                let p = match p.as_object() {
+                    Some(p) => p,
+                    None => return None,
+                };
+                let field = |f: &str| p.get(f).and_then(|x| x.as_string()).unwrap_or("").to_owned();
+                Some(Protection {
+                    action: field("type"),
+                    level: field("level"),
+                    expiry: field("expiry"),
+                })
+            }).collect())
+            .unwrap_or_default(),
+    })
+}
+
+/// Adds the `disambiguation` page property to `params`, which must have a
+/// `prop`.
+fn disambiguation_params(params: &[(&str, &str)]) -> Vec<(String, String)> {
+    let mut params: Vec<_> = params
+        .iter()
+        .map(|&(k, v)| match k {
+            "prop" => (k.to_owned(), format!("{}|pageprops", v)),
+            _ => (k.to_owned(), v.to_owned()),
+        })
+        .collect();
+    params.insert(1, ("ppprop".to_owned(), "disambiguation".to_owned()));
+    params
+}
+
+/// Returns true if `page`, from a query that requested the
+/// `disambiguation` page property, is a disambiguation page.
+fn is_disambiguation(page: &BTreeMap<String, serde_json::Value>) -> bool {
+    page.get("pageprops")
+        .and_then(|x| x.as_object())
+        .map(|x| x.contains_key("disambiguation"))
+        .unwrap_or(false)
+}
+
+fn sort_disambiguation_options(mut options: Vec<DisambiguationOption>) -> Vec<DisambiguationOption> {
+    options.sort_by(|a, b| a.title.cmp(&b.title));
+    options.dedup();
+    options
+}
+
+fn disambiguation_error(options: Vec<DisambiguationOption>) -> Error {
+    Error::Disambiguation(options.into_iter().map(|o| o.title).collect())
+}
+
+fn parse_disambiguation_options(q: &serde_json::Value) -> Vec<DisambiguationOption> {
+    parse_pages(q)
+        .map(|pages| pages.values().filter_map(|p| {
+            let p = match p.as_object() {
+                Some(p) => p,
+                None => return None,
+            };
+            if p.contains_key("missing") || p.contains_key("invalid") {
+                return None;
+            }
+            p.get("title").and_then(|x| x.as_string()).map(|title| DisambiguationOption {
+                title: title.to_owned(),
+                description: p
+                    .get("description")
+                    .and_then(|x| x.as_string())
+                    .map(|x| x.to_owned()),
+            })
+        }).collect())
+        .unwrap_or_default()
+}
+
+fn parse_extract(q: &serde_json::Value) -> Result<String> {
+    Ok(try!(try!(first_page(q))
+        .get("extract")
+        .and_then(|x| x.as_string())
+        .ok_or(Error::JSONPathError))
+        .to_owned())
+}
 
-    /// Receive a json object and extracts any `continue` parameters to be
-    /// used when browsing following pages.
-    fn parse_cont(&self, q: &serde_json::Value) -> Result<Option<Vec<(String, String)>>> {
-        let cont = match q
-            .as_object()
-            .and_then(|x| x.get("continue"))
-            .and_then(|x| x.as_object()) {
-            Some(v) => v,
-            None => return Ok(None),
-        };
-        let mut cont_v = vec![];
-        for (k, v) in cont.into_iter() {
-            let value = match *v {
-                serde_json::Value::Null => "".to_owned(),
-                serde_json::Value::Bool(b) => if b { "1" } else { "0" }.to_owned(),
-                serde_json::Value::I64(i) => format!("{}", i),
-                serde_json::Value::U64(u) => format!("{}", u),
-                serde_json::Value::F64(f) => format!("{}", f),
-                serde_json::Value::String(ref s) => s.clone(),
-                _ => return Err(Error::JSONPathError),
-            };
-            cont_v.push((k.clone(), value));
-        }
-        Ok(Some(cont_v))
-    }
+fn parse_revision(q: &serde_json::Value) -> Result<String> {
+    Ok(try!(try!(first_page(q))
+        .get("revisions")
+        .and_then(|x| x.as_array())
+        .and_then(|x| x.iter().next())
+        .and_then(|x| x.as_object())
+        .and_then(|x| x.get("*"))
+        .and_then(|x| x.as_string())
+        .ok_or(Error::JSONPathError))
+        .to_owned())
+}
 
-    fn request_images(&self, cont: &Option<Vec<(String, String)>>) ->
-            Result<(Vec<serde_json::Value>, Option<Vec<(String, String)>>)> {
-        cont!(self, cont,
-            ("generator", "images"),
-            ("gimlimit", &*self.wikipedia.images_results),
-            ("prop", "imageinfo"),
-            ("iiprop", "url")
-        )
-    }
+fn parse_wikitext(q: &serde_json::Value) -> Result<String> {
+    Ok(try!(try!(first_page(q))
+        .get("revisions")
+        .and_then(|x| x.as_array())
+        .and_then(|x| x.iter().next())
+        .and_then(|x| x.as_object())
+        .and_then(|x| x.get("slots"))
+        .and_then(|x| x.as_object())
+        .and_then(|x| x.get("main"))
+        .and_then(|x| x.as_object())
+        .and_then(|x| x.get("*"))
+        .and_then(|x| x.as_string())
+        .ok_or(Error::JSONPathError))
+        .to_owned())
+}
 
-    /// Creates an iterator to view all images in the `Page`.
-    pub fn get_images(&self) -> Result<Iter<A, iter::Image>> {
-        Iter::new(&self)
-    }
+fn parse_coordinates(q: &serde_json::Value) -> Result<Option<(f64, f64)>> {
+    let coord = match try!(first_page(q))
+            .get("coordinates")
+            .and_then(|x| x.as_array())
+            .and_then(|x| x.iter().next())
+            .and_then(|x| x.as_object()) {
+        Some(c) => c,
+        None => return Ok(None),
+    };
+    Ok(Some((
+        try!(coord.get("lat").and_then(|x| x.as_f64()).ok_or(Error::JSONPathError)),
+        try!(coord.get("lon").and_then(|x| x.as_f64()).ok_or(Error::JSONPathError)),
+    )))
+}
 
-    fn request_extlinks(&self, cont: &Option<Vec<(String, String)>>) ->
-            Result<(Vec<serde_json::Value>, Option<Vec<(String, String)>>)> {
-        let a:Result<(Vec<serde_json::Value>, _)> = cont!(self, cont,
-            ("prop", "extlinks"),
-            ("ellimit", &*self.wikipedia.links_results)
-        );
-        a.map(|(pages, cont)| {
-            let page = match pages.into_iter().next() {
-                Some(p) => p,
-                None => return (Vec::new(), None),
+fn parse_sections(q: &serde_json::Value) -> Result<Vec<String>> {
+    Ok(try!(q
+        .as_object()
+        .and_then(|x| x.get("parse"))
+        .and_then(|x| x.as_object())
+        .and_then(|x| x.get("sections"))
+        .and_then(|x| x.as_array())
+        .ok_or(Error::JSONPathError))
+        .iter()
+        .filter_map(|x| x.as_object()
+                .and_then(|x| x.get("line"))
+                .and_then(|x| x.as_string())
+                .map(|x| x.to_owned())
+                )
+        .collect())
+}
+
+/// Returns the sections of an `action=parse` response, without nesting them.
+fn parse_section_list(q: &serde_json::Value) -> Result<Vec<Section>> {
+    Ok(try!(q
+        .as_object()
+        .and_then(|x| x.get("parse"))
+        .and_then(|x| x.as_object())
+        .and_then(|x| x.get("sections"))
+        .and_then(|x| x.as_array())
+        .ok_or(Error::JSONPathError))
+        .iter()
+        .filter_map(|x| {
+            let o = match x.as_object() {
+                Some(o) => o,
+                None => return None,
             };
-            (page
-                .as_object()
-                .and_then(|x| x.get("extlinks"))
-                .and_then(|x| x.as_array())
-                .map(|x| x.into_iter().cloned().collect())
-                .unwrap_or(Vec::new()), cont)
+            let field = |f: &str| o.get(f).and_then(|x| x.as_string()).unwrap_or("").to_owned();
+            o.get("line").and_then(|x| x.as_string()).map(|title| Section {
+                title: title.to_owned(),
+                level: field("level").parse().unwrap_or(2),
+                number: field("number"),
+                index: field("index"),
+                anchor: field("anchor"),
+                byte_offset: o.get("byteoffset").and_then(|x| x.as_u64()),
+                children: Vec::new(),
+            })
         })
-    }
+        .collect())
+}
 
-    /// Creates an iterator to view all references (external links) in the `Page`.
-    pub fn get_references(&self) -> Result<Iter<A, iter::Reference>> {
-        Iter::new(&self)
+/// Nests a flat list of sections by level.
+fn section_tree(sections: Vec<Section>) -> Vec<Section> {
+    let mut tree = Vec::new();
+    for section in sections {
+        add_section(&mut tree, section);
     }
+    tree
+}
 
-    fn request_links(&self, cont: &Option<Vec<(String, String)>>) ->
-            Result<(Vec<serde_json::Value>, Option<Vec<(String, String)>>)> {
-        let a:Result<(Vec<serde_json::Value>, _)> = cont!(self, cont,
-            ("prop", "links"),
-            ("plnamespace", "0"),
-            ("ellimit", &*self.wikipedia.links_results)
-        );
-        a.map(|(pages, cont)| {
-            let page = match pages.into_iter().next() {
-                Some(p) => p,
-                None => return (Vec::new(), None),
-            };
-            (page
-                .as_object()
-                .and_then(|x| x.get("links"))
-                .and_then(|x| x.as_array())
-                .map(|x| x.into_iter().cloned().collect())
-                .unwrap_or(Vec::new()), cont)
-        })
+fn add_section(siblings: &mut Vec<Section>, section: Section) {
+    match siblings.last_mut() {
+        Some(last) if last.level < section.level => return add_section(&mut last.children, section),
+        _ => (),
     }
+    siblings.push(section);
+}
 
-    /// Creates an iterator to view all internal links in the `Page`.
-    pub fn get_links(&self) -> Result<Iter<A, iter::Link>> {
-        Iter::new(&self)
+/// A `== title ==` line in plain text or wikitext.
+struct Heading {
+    /// Byte offset of the start of the line.
+    start: usize,
+    /// Byte offset of the end of the line.
+    end: usize,
+    level: u8,
+    title: String,
+}
+
+/// Finds all heading lines in `text`, in order.
+fn headings(text: &str) -> Vec<Heading> {
+    let mut headings = Vec::new();
+    let mut start = 0;
+    for line in text.split('\n') {
+        let end = start + line.len();
+        if let Some((level, title)) = parse_heading(line) {
+            headings.push(Heading { start, end, level, title });
+        }
+        start = end + 1;
     }
+    headings
+}
 
-    fn request_categories(&self, cont: &Option<Vec<(String, String)>>) ->
-            Result<(Vec<serde_json::Value>, Option<Vec<(String, String)>>)> {
-        let a:Result<(Vec<serde_json::Value>, _)> = cont!(self, cont,
-            ("prop", "categories"),
-            ("cllimit", &*self.wikipedia.categories_results)
-        );
-        a.map(|(pages, cont)| {
-            let page = match pages.into_iter().next() {
-                Some(p) => p,
-                None => return (Vec::new(), None),
-            };
-            (page
-                .as_object()
-                .and_then(|x| x.get("categories"))
-                .and_then(|x| x.as_array())
-                .map(|x| x.into_iter().cloned().collect())
-                .unwrap_or(Vec::new()), cont)
-        })
+/// Returns the level and title of a heading line.
+fn parse_heading(line: &str) -> Option<(u8, String)> {
+    let line = line.trim();
+    let level = line.len() - line.trim_start_matches('=').len();
+    let trailing = line.len() - line.trim_end_matches('=').len();
+    if level == 0 || level > 6 || line.len() <= level * 2 || trailing != level {
+        return None;
     }
+    let title = line[level..line.len() - level].trim();
+    if title.is_empty() || title.starts_with('=') || title.ends_with('=') {
+        return None;
+    }
+    Some((level as u8, title.to_owned()))
+}
 
-    /// Creates an iterator to view all categories of the `Page`.
-    pub fn get_categories(&self) -> Result<Iter<A, iter::Category>> {
-        Iter::new(&self)
+/// Renders wikitext as plain text, with at most one blank line between
+/// paragraphs.
+fn wikitext_text(wikitext: &str) -> String {
+    let text = wikitext::plain_text(&wikitext::parse(wikitext));
+    let mut lines: Vec<&str> = Vec::new();
+    for line in text.lines().map(|l| l.trim_end()) {
+        if !line.is_empty() || lines.last().map(|l| !l.is_empty()).unwrap_or(false) {
+            lines.push(line);
+        }
     }
+    lines.join("\n").trim_end().to_owned()
+}
 
-    /// Returns the latitude and longitude associated to the `Page` if any.
-    pub fn get_coordinates(&self) -> Result<Option<(f64, f64)>> {
-        let qp = self.identifier.query_param();
-        let params = vec![
-            ("prop", "coordinates"),
-            ("colimit", "max"),
-            ("redirects", ""),
-            ("format", "json"),
-            ("action", "query"),
-            (&*qp.0, &*qp.1),
-        ];
-        let q = try!(self.wikipedia.query(params.into_iter()));
+/// Returns the text under `headings[i]`. It ends at the next heading of the
+/// same or a lower level, or at the next heading of any level if
+/// `subsections` is false.
+fn section_body(text: &str, headings: &[Heading], i: usize, subsections: bool) -> String {
+    let level = headings[i].level;
+    let end = headings[i + 1..]
+        .iter()
+        .find(|h| !subsections || h.level <= level)
+        .map(|h| h.start)
+        .unwrap_or(text.len());
+    text[headings[i].end..end].trim().to_owned()
+}
 
-        match self.redirect(&q) {
-            Some(r) => return Page::from_title(&self.wikipedia, r).get_coordinates(),
-            None => (),
-        }
-        let pages = try!(q
-            .as_object()
-            .and_then(|x| x.get("query"))
-            .and_then(|x| x.as_object())
-            .and_then(|x| x.get("pages"))
-            .and_then(|x| x.as_object())
-            .ok_or(Error::JSONPathError));
-        let pageid = match pages.keys().next() {
-            Some(p) => p,
-            None => return Err(Error::JSONPathError),
-        };
-        let coord = match pages.get(pageid)
-                .and_then(|x| x.as_object())
-                .and_then(|x| x.get("coordinates"))
-                .and_then(|x| x.as_array())
-                .and_then(|x| x.into_iter().next())
-                .and_then(|x| x.as_object()) {
-            Some(c) => c,
-            None => return Ok(None),
+/// Finds the first section `title` in the plain text `content`, at any
+/// level, including its subsections.
+fn section_content(content: &str, title: &str) -> Option<String> {
+    let headings = headings(content);
+    headings
+        .iter()
+        .position(|h| h.title == title)
+        .map(|i| section_body(content, &headings, i, true))
+}
+
+/// Returns the `field` of an `action=parse` response.
+fn parse_field(q: &serde_json::Value, field: &str) -> Result<String> {
+    Ok(try!(q
+        .as_object()
+        .and_then(|x| x.get("parse"))
+        .and_then(|x| x.as_object())
+        .and_then(|x| x.get(field))
+        .and_then(|x| x.as_object())
+        .and_then(|x| x.get("*"))
+        .and_then(|x| x.as_string())
+        .ok_or(Error::JSONPathError))
+        .to_owned())
+}
+
+/// Receive a json object and extracts any `continue` parameters to be
+/// used when browsing following pages.
+fn parse_cont(q: &serde_json::Value) -> Result<Option<Vec<(String, String)>>> {
+    let cont = match q
+        .as_object()
+        .and_then(|x| x.get("continue"))
+        .and_then(|x| x.as_object()) {
+        Some(v) => v,
+        None => return Ok(None),
+    };
+    let mut cont_v = vec![];
+    for (k, v) in cont {
+        let value = match *v {
+            serde_json::Value::Null => "".to_owned(),
+            serde_json::Value::Bool(b) => if b { "1" } else { "0" }.to_owned(),
+            serde_json::Value::I64(i) => format!("{}", i),
+            serde_json::Value::U64(u) => format!("{}", u),
+            serde_json::Value::F64(f) => format!("{}", f),
+            serde_json::Value::String(ref s) => s.clone(),
+            _ => return Err(Error::JSONPathError),
         };
-        Ok(Some((
-            try!(coord.get("lat").and_then(|x| x.as_f64()).ok_or(Error::JSONPathError)),
-            try!(coord.get("lon").and_then(|x| x.as_f64()).ok_or(Error::JSONPathError)),
-        )))
+        cont_v.push((k.clone(), value));
     }
+    Ok(Some(cont_v))
+}
 
-    /// Fetches all sections of the article.
-    pub fn get_sections(&self) -> Result<Vec<String>> {
-        let pageid = try!(self.get_pageid());
-        let params = vec![
-            ("prop", "sections"),
-            ("format", "json"),
-            ("action", "parse"),
-            ("pageid", &*pageid),
-        ];
-        let q = try!(self.wikipedia.query(params.into_iter()));
+/// Returns all pages in the response and its `continue` parameters.
+fn parse_cont_pages(q: &serde_json::Value)
+        -> Result<(Vec<serde_json::Value>, Option<Vec<(String, String)>>)> {
+    let pages = try!(parse_pages(q));
+    Ok((pages.values().cloned().collect(), try!(parse_cont(q))))
+}
 
-        Ok(try!(q
-            .as_object()
-            .and_then(|x| x.get("parse"))
-            .and_then(|x| x.as_object())
-            .and_then(|x| x.get("sections"))
-            .and_then(|x| x.as_array())
-            .ok_or(Error::JSONPathError))
-            .into_iter()
-            .filter_map(|x| x.as_object()
-                    .and_then(|x| x.get("line"))
-                    .and_then(|x| x.as_string())
-                    .map(|x| x.to_owned())
-                    )
-            .collect())
-    }
+/// Returns the items of a `ContQuery` with `field` in the response and its
+/// `continue` parameters.
+fn parse_cont_items(q: &serde_json::Value, field: Option<&str>)
+        -> Result<(Vec<serde_json::Value>, Option<Vec<(String, String)>>)> {
+    let (pages, cont) = try!(parse_cont_pages(q));
+    Ok(match field {
+        Some(field) => first_page_array(pages, cont, field),
+        None => (pages, cont),
+    })
+}
 
-    /// Fetches the content of a section.
-    pub fn get_section_content(&self, title: &str) -> Result<Option<String>> {
-        let headr = format!("== {} ==", title);
-        let content = try!(self.get_content());
-        let index = match content.find(&*headr) {
-            Some(i) => headr.len() + i,
-            None => return Ok(None),
+/// Returns the `list` results in `field` and the `continue` parameters.
+fn parse_cont_list(q: &serde_json::Value, field: &str)
+        -> Result<(Vec<serde_json::Value>, Option<Vec<(String, String)>>)> {
+    let list = try!(q
+        .as_object()
+        .and_then(|x| x.get("query"))
+        .and_then(|x| x.as_object())
+        .and_then(|x| x.get(field))
+        .and_then(|x| x.as_array())
+        .ok_or(Error::JSONPathError));
+    Ok((list.clone(), try!(parse_cont(q))))
+}
+
+/// Moves the pages linking through a redirect, listed in its `redirlinks`,
+/// right after it, with the redirect's title as `via`.
+fn backlink_values(links: Vec<serde_json::Value>) -> Vec<serde_json::Value> {
+    let mut values = Vec::new();
+    for link in links {
+        let mut obj = match link {
+            serde_json::Value::Object(o) => o,
+            _ => continue,
         };
-        let end = match content[index..].find("==") {
-            Some(i) => index + i,
-            None => content.len(),
+        let redirlinks = obj.remove("redirlinks");
+        let title = obj.get("title").cloned();
+        values.push(serde_json::Value::Object(obj));
+        let (redirlinks, title) = match (redirlinks, title) {
+            (Some(serde_json::Value::Array(r)), Some(t)) => (r, t),
+            _ => continue,
         };
-        Ok(Some(content[index..end].to_owned()))
+        for redirlink in redirlinks {
+            if let serde_json::Value::Object(mut r) = redirlink {
+                r.insert("via".to_owned(), title.clone());
+                values.push(serde_json::Value::Object(r));
+            }
+        }
     }
+    values
 }
 
-impl<'a, A: http::HttpClient> PartialEq<Page<'a, A>> for Page<'a, A> {
-    fn eq(&self, other: &Page<A>) -> bool {
-        match self.identifier {
-            TitlePageId::Title(ref t1) => match other.identifier {
-                TitlePageId::Title(ref t2) => t1 == t2,
-                TitlePageId::PageId(_) => false,
-            },
-            TitlePageId::PageId(ref p1) => match other.identifier {
-                TitlePageId::Title(_) => false,
-                TitlePageId::PageId(ref p2) => p1 == p2,
-            },
-        }
-    }
+/// Takes the array `field` out of the first page in `pages`.
+fn first_page_array(pages: Vec<serde_json::Value>, cont: Option<Vec<(String, String)>>,
+        field: &str) -> (Vec<serde_json::Value>, Option<Vec<(String, String)>>) {
+    let page = match pages.into_iter().next() {
+        Some(p) => p,
+        None => return (Vec::new(), None),
+    };
+    (page
+        .as_object()
+        .and_then(|x| x.get(field))
+        .and_then(|x| x.as_array())
+        .map(|x| x.to_vec())
+        .unwrap_or_default(), cont)
 }
 
+
 #[cfg(test)]
 mod test {
-    use super::Wikipedia;
+    use super::{Error, Iter, Wikipedia};
+    use super::http;
     use super::http::HttpClient;
     use super::iter;
-    use std::sync::Mutex;
+    use std::error::Error as StdError;
+    use std::io;
+    use std::sync::{Arc, Mutex};
 
     struct MockClient {
         pub url: Mutex<Vec<String>>,
@@ -1282,4 +2413,646 @@ mod test {
                     ("action".to_owned(), "query".to_owned())
                     ]]);
     }
+
+    #[test]
+    fn api_error() {
+        let wikipedia = Wikipedia::<MockClient>::default();
+        wikipedia.client.response.lock().unwrap().push("{\"error\":{\"code\":\"badvalue\",\"info\":\"Unrecognized value\"}}".to_owned());
+        match wikipedia.search("hello world").unwrap_err() {
+            Error::ApiError { code, info } => {
+                assert_eq!(code, "badvalue".to_owned());
+                assert_eq!(info, "Unrecognized value".to_owned());
+            },
+            e => panic!("unexpected error {:?}", e),
+        }
+    }
+
+    #[test]
+    fn http_error_source() {
+        let e = Error::from(http::Error::from_source(io::Error::other("reset")));
+        assert_eq!(format!("{}", e), "HTTP request failed: reset");
+        let source = e.source().unwrap();
+        assert_eq!(format!("{}", source.source().unwrap()), "reset");
+
+        let e = http::Error::from_status(503, vec![("Retry-After".to_owned(), "5".to_owned())]);
+        assert_eq!(e.status, Some(503));
+        assert_eq!(e.header("retry-after"), Some("5"));
+        assert!(e.source().is_none());
+    }
+
+    #[test]
+    fn get_links_retry() {
+        let wikipedia = Wikipedia::<MockClient>::default();
+        wikipedia.client.response.lock().unwrap().push("{\"continue\": {\"lol\":\"1\"},\"query\":{\"pages\":{\"a\":{\"links\":[{\"title\": \"Hello\"}]}}}}".to_owned());
+        wikipedia.client.response.lock().unwrap().push("{\"query\":".to_owned());
+        wikipedia.client.response.lock().unwrap().push("{\"query\":{\"pages\":{\"a\":{\"links\":[{\"title\": \"World\"}]}}}}".to_owned());
+        let page = wikipedia.page_from_title("World".to_owned());
+        let mut links = page.get_links().unwrap().results();
+        assert_eq!(links.next().unwrap().unwrap(), iter::Link { title: "Hello".to_owned() });
+        match links.next() {
+            Some(Err(Error::JSONError(_))) => (),
+            r => panic!("unexpected result {:?}", r),
+        }
+        assert_eq!(links.next().unwrap().unwrap(), iter::Link { title: "World".to_owned() });
+        assert!(links.next().is_none());
+        let arguments = wikipedia.client.arguments.lock().unwrap();
+        assert_eq!(arguments.len(), 3);
+        assert_eq!(arguments[1].last().unwrap(), &("lol".to_owned(), "1".to_owned()));
+        assert_eq!(arguments[2].last().unwrap(), &("lol".to_owned(), "1".to_owned()));
+    }
+
+    #[test]
+    fn get_links_invalid_item() {
+        let wikipedia = Wikipedia::<MockClient>::default();
+        for _ in 0..2 {
+            wikipedia.client.response.lock().unwrap().push("{\"query\":{\"pages\":{\"a\":{\"links\":[{\"title\": \"Hello\"}, {\"ns\": 0}, {\"title\": \"World\"}]}}}}".to_owned());
+        }
+        let page = wikipedia.page_from_title("World".to_owned());
+        let mut links = page.get_links().unwrap().results();
+        assert_eq!(links.next().unwrap().unwrap(), iter::Link { title: "Hello".to_owned() });
+        match links.next() {
+            Some(Err(Error::JSONPathError)) => (),
+            r => panic!("unexpected result {:?}", r),
+        }
+        assert_eq!(links.next().unwrap().unwrap(), iter::Link { title: "World".to_owned() });
+        assert!(links.next().is_none());
+        assert_eq!(page.get_links().unwrap().count(), 1);
+    }
+
+    #[test]
+    fn get_links_resume() {
+        let wikipedia = Wikipedia::<MockClient>::default();
+        wikipedia.client.response.lock().unwrap().push("{\"continue\": {\"lol\":\"1\"},\"query\":{\"pages\":{\"a\":{\"links\":[{\"title\": \"Hello\"}]}}}}".to_owned());
+        wikipedia.client.response.lock().unwrap().push("{\"query\":{\"pages\":{\"a\":{\"links\":[{\"title\": \"World\"}, {\"title\": \"Foo\"}]}}}}".to_owned());
+        let page = wikipedia.page_from_title("World".to_owned());
+        let mut links = page.get_links().unwrap();
+        assert_eq!(links.next().unwrap(), iter::Link { title: "Hello".to_owned() });
+        assert_eq!(links.next().unwrap(), iter::Link { title: "World".to_owned() });
+        let token = links.continuation().to_string();
+        assert_eq!(token, "{\"continue\":{\"lol\":\"1\"},\"offset\":1}");
+
+        wikipedia.client.response.lock().unwrap().push("{\"query\":{\"pages\":{\"a\":{\"links\":[{\"title\": \"World\"}, {\"title\": \"Foo\"}]}}}}".to_owned());
+        let continuation = token.parse::<iter::Continuation>().unwrap();
+        let links = Iter::<_, iter::Link>::resume(&page, &continuation).unwrap();
+        assert_eq!(links.collect::<Vec<_>>(), vec![iter::Link { title: "Foo".to_owned() }]);
+        let arguments = wikipedia.client.arguments.lock().unwrap();
+        assert_eq!(arguments.len(), 3);
+        assert_eq!(arguments[2], arguments[1]);
+    }
+
+    #[test]
+    fn pages() {
+        let wikipedia = Wikipedia::<MockClient> {
+            titles_per_request: 2,
+            ..Wikipedia::default()
+        };
+        wikipedia.client.response.lock().unwrap().push("{\"continue\":{\"excontinue\":\"1\",\"continue\":\"||\"},\"query\":{\"normalized\":[{\"from\":\"nYC\",\"to\":\"NYC\"}],\"redirects\":[{\"from\":\"NYC\",\"to\":\"New York City\"}],\"pages\":{\"645042\":{\"pageid\":645042,\"title\":\"New York City\",\"extract\":\"hello\",\"coordinates\":[{\"lat\":40.7,\"lon\":-74.0}]},\"1\":{\"pageid\":1,\"title\":\"World\"}}}}".to_owned());
+        wikipedia.client.response.lock().unwrap().push("{\"query\":{\"pages\":{\"645042\":{\"pageid\":645042,\"title\":\"New York City\"},\"1\":{\"pageid\":1,\"title\":\"World\",\"extract\":\"world\"}}}}".to_owned());
+        wikipedia.client.response.lock().unwrap().push("{\"query\":{\"pages\":{\"-1\":{\"title\":\"Foo bar baz\",\"missing\":\"\"}}}}".to_owned());
+        let pages = wikipedia.pages(&["nYC", "World", "Foo bar baz"]).unwrap();
+        assert_eq!(pages.len(), 3);
+        assert_eq!(pages["NYC"], super::BatchPage {
+            title: "New York City".to_owned(),
+            pageid: Some("645042".to_owned()),
+            summary: Some("hello".to_owned()),
+            content: None,
+            coordinates: Some((40.7, -74.0)),
+            missing: false,
+            invalid: false,
+        });
+        assert_eq!(pages["World"].summary, Some("world".to_owned()));
+        assert!(pages["Foo bar baz"].missing);
+        let arguments = wikipedia.client.arguments.lock().unwrap();
+        assert_eq!(arguments.len(), 3);
+        assert_eq!(arguments[0], vec![
+            ("prop".to_owned(), "extracts|coordinates".to_owned()),
+            ("explaintext".to_owned(), "".to_owned()),
+            ("exlimit".to_owned(), "max".to_owned()),
+            ("colimit".to_owned(), "max".to_owned()),
+            ("redirects".to_owned(), "".to_owned()),
+            ("exintro".to_owned(), "".to_owned()),
+            ("format".to_owned(), "json".to_owned()),
+            ("action".to_owned(), "query".to_owned()),
+            ("titles".to_owned(), "nYC|World".to_owned()),
+            ("continue".to_owned(), "".to_owned()),
+            ]);
+        assert_eq!(arguments[1][9..].to_vec(), vec![
+            ("continue".to_owned(), "||".to_owned()),
+            ("excontinue".to_owned(), "1".to_owned()),
+            ]);
+        assert_eq!(arguments[2][8], ("titles".to_owned(), "Foo bar baz".to_owned()));
+    }
+
+    #[test]
+    fn maxlag() {
+        let wikipedia = Wikipedia::<MockClient> {
+            maxlag: Some(5),
+            ..Wikipedia::default()
+        };
+        wikipedia.client.response.lock().unwrap().push("{\"query\":{\"random\":[{\"title\":\"hello\"}]}}".to_owned());
+        wikipedia.random().unwrap();
+        assert_eq!(*wikipedia.client.arguments.lock().unwrap(),
+                vec![vec![
+                    ("list".to_owned(), "random".to_owned()),
+                    ("rnnamespace".to_owned(), "0".to_owned()),
+                    ("rnlimit".to_owned(), "1".to_owned()),
+                    ("format".to_owned(), "json".to_owned()),
+                    ("action".to_owned(), "query".to_owned()),
+                    ("maxlag".to_owned(), "5".to_owned()),
+                    ]]);
+    }
+
+    #[test]
+    fn page_info() {
+        let wikipedia = Wikipedia::<MockClient>::default();
+        wikipedia.client.response.lock().unwrap().push("{\"query\":{\"pages\":{\"4138548\":{\"pageid\":4138548,\"ns\":0,\"title\":\"Law of triviality\",\"contentmodel\":\"wikitext\",\"pagelanguage\":\"en\",\"touched\":\"2016-01-01T00:00:00Z\",\"lastrevid\":123,\"length\":456,\"protection\":[{\"type\":\"move\",\"level\":\"sysop\",\"expiry\":\"infinity\"}],\"fullurl\":\"https://en.wikipedia.org/wiki/Law_of_triviality\",\"editurl\":\"https://en.wikipedia.org/w/index.php?title=Law_of_triviality&action=edit\",\"pageprops\":{\"wikibase_item\":\"Q1475380\"}}}}}".to_owned());
+        let page = wikipedia.page_from_title("Law of triviality".to_owned());
+        assert_eq!(page.get_info().unwrap(), super::PageInfo {
+            pageid: "4138548".to_owned(),
+            ns: 0,
+            title: "Law of triviality".to_owned(),
+            full_url: "https://en.wikipedia.org/wiki/Law_of_triviality".to_owned(),
+            edit_url: "https://en.wikipedia.org/w/index.php?title=Law_of_triviality&action=edit".to_owned(),
+            touched: "2016-01-01T00:00:00Z".to_owned(),
+            last_rev_id: 123,
+            length: 456,
+            content_model: "wikitext".to_owned(),
+            language: "en".to_owned(),
+            disambiguation: false,
+            wikibase_item: Some("Q1475380".to_owned()),
+            protection: vec![super::Protection {
+                action: "move".to_owned(),
+                level: "sysop".to_owned(),
+                expiry: "infinity".to_owned(),
+            }],
+        });
+        assert_eq!(*wikipedia.client.arguments.lock().unwrap(),
+                vec![vec![
+                    ("prop".to_owned(), "info|pageprops".to_owned()),
+                    ("inprop".to_owned(), "url|protection".to_owned()),
+                    ("ppprop".to_owned(), "disambiguation|wikibase_item".to_owned()),
+                    ("redirects".to_owned(), "".to_owned()),
+                    ("format".to_owned(), "json".to_owned()),
+                    ("action".to_owned(), "query".to_owned()),
+                    ("titles".to_owned(), "Law of triviality".to_owned())
+                    ]]);
+    }
+
+    #[test]
+    fn page_info_redirect() {
+        let wikipedia = Wikipedia::<MockClient>::default();
+        wikipedia.client.response.lock().unwrap().push("{\"query\":{\"redirects\":[{\"from\":\"Bikeshedding\",\"to\":\"Law of triviality\"}],\"pages\":{\"4138548\":{\"pageid\":4138548,\"ns\":0,\"title\":\"Law of triviality\"}}}}".to_owned());
+        let page = wikipedia.page_from_title("Bikeshedding".to_owned());
+        let info = page.get_info().unwrap();
+        assert_eq!(info.pageid, "4138548".to_owned());
+        assert_eq!(info.title, "Law of triviality".to_owned());
+        assert_eq!(wikipedia.client.arguments.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn page_title() {
+        let wikipedia = Wikipedia::<MockClient>::default();
+        wikipedia.client.response.lock().unwrap().push("{\"query\":{\"pages\":{\"4138548\":{\"pageid\":4138548,\"title\":\"Law of triviality\",\"pageprops\":{\"disambiguation\":\"\"}}}}}".to_owned());
+        let page = wikipedia.page_from_pageid("4138548".to_owned());
+        assert_eq!(page.get_title().unwrap(), "Law of triviality".to_owned());
+        assert_eq!(page.get_pageid().unwrap(), "4138548".to_owned());
+        assert_eq!(wikipedia.client.arguments.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn disambiguation() {
+        let wikipedia = Wikipedia::<MockClient> {
+            disambiguation_errors: true,
+            ..Wikipedia::default()
+        };
+        wikipedia.client.response.lock().unwrap().push("{\"query\":{\"pages\":{\"19694\":{\"pageid\":19694,\"title\":\"Mercury\",\"extract\":\"Mercury may refer to:\",\"pageprops\":{\"disambiguation\":\"\"}}}}}".to_owned());
+        wikipedia.client.response.lock().unwrap().push("{\"continue\":{\"gplcontinue\":\"19694|0|Mercury_(planet)\",\"continue\":\"gplcontinue||\"},\"query\":{\"pages\":{\"19007\":{\"pageid\":19007,\"title\":\"Mercury (element)\",\"description\":\"Chemical element with atomic number 80\"}}}}".to_owned());
+        wikipedia.client.response.lock().unwrap().push("{\"query\":{\"pages\":{\"19001\":{\"pageid\":19001,\"title\":\"Mercury (planet)\",\"description\":\"Planet in the Solar System\"},\"-1\":{\"title\":\"Mercury (band)\",\"missing\":\"\"}}}}".to_owned());
+        let page = wikipedia.page_from_title("Mercury".to_owned());
+        match page.get_summary() {
+            Err(Error::Disambiguation(titles)) => assert_eq!(titles, vec![
+                "Mercury (element)".to_owned(),
+                "Mercury (planet)".to_owned(),
+            ]),
+            r => panic!("unexpected {:?}", r),
+        }
+        let arguments = wikipedia.client.arguments.lock().unwrap();
+        assert_eq!(arguments.len(), 3);
+        assert_eq!(arguments[0][..2].to_vec(), vec![
+            ("prop".to_owned(), "extracts|pageprops".to_owned()),
+            ("ppprop".to_owned(), "disambiguation".to_owned()),
+        ]);
+        assert_eq!(arguments[2], vec![
+            ("generator".to_owned(), "links".to_owned()),
+            ("gplnamespace".to_owned(), "0".to_owned()),
+            ("gpllimit".to_owned(), "max".to_owned()),
+            ("prop".to_owned(), "description".to_owned()),
+            ("redirects".to_owned(), "".to_owned()),
+            ("format".to_owned(), "json".to_owned()),
+            ("action".to_owned(), "query".to_owned()),
+            ("titles".to_owned(), "Mercury".to_owned()),
+            ("continue".to_owned(), "gplcontinue||".to_owned()),
+            ("gplcontinue".to_owned(), "19694|0|Mercury_(planet)".to_owned()),
+        ]);
+    }
+
+    #[test]
+    fn disambiguation_options() {
+        let wikipedia = Wikipedia::<MockClient>::default();
+        wikipedia.client.response.lock().unwrap().push("{\"query\":{\"pages\":{\"19001\":{\"pageid\":19001,\"title\":\"Mercury (planet)\",\"description\":\"Planet in the Solar System\"},\"19007\":{\"pageid\":19007,\"title\":\"Mercury (element)\"}}}}".to_owned());
+        let page = wikipedia.page_from_title("Mercury".to_owned());
+        assert_eq!(page.disambiguation_options().unwrap(), vec![
+            super::DisambiguationOption {
+                title: "Mercury (element)".to_owned(),
+                description: None,
+            },
+            super::DisambiguationOption {
+                title: "Mercury (planet)".to_owned(),
+                description: Some("Planet in the Solar System".to_owned()),
+            },
+        ]);
+    }
+
+    #[test]
+    fn resolve() {
+        let wikipedia = Wikipedia::<MockClient>::default();
+        wikipedia.client.response.lock().unwrap().push("{\"query\":{\"normalized\":[{\"from\":\"nYC\",\"to\":\"NYC\"}],\"redirects\":[{\"from\":\"NYC\",\"to\":\"New York (city)\"},{\"from\":\"New York (city)\",\"to\":\"New York City\",\"tofragment\":\"History\"}],\"pages\":{\"645042\":{\"pageid\":645042,\"ns\":0,\"title\":\"New York City\"}}}}".to_owned());
+        wikipedia.client.response.lock().unwrap().push("{\"query\":{\"pages\":{\"645042\":{\"pageid\":645042,\"ns\":0,\"title\":\"New York City\"}}}}".to_owned());
+        let (page, resolution) = wikipedia.page_from_title("nYC".to_owned()).resolve().unwrap();
+        assert_eq!(resolution, super::Resolution {
+            normalized: Some("NYC".to_owned()),
+            redirects: vec![
+                super::Redirect {
+                    from: "NYC".to_owned(),
+                    to: "New York (city)".to_owned(),
+                    fragment: None,
+                },
+                super::Redirect {
+                    from: "New York (city)".to_owned(),
+                    to: "New York City".to_owned(),
+                    fragment: Some("History".to_owned()),
+                },
+            ],
+            title: "New York City".to_owned(),
+            pageid: Some("645042".to_owned()),
+            fragment: Some("History".to_owned()),
+        });
+        let (other, _) = wikipedia.page_from_title("New York City".to_owned()).resolve().unwrap();
+        assert!(page == other);
+        assert!(page == wikipedia.page_from_pageid("645042".to_owned()));
+    }
+
+    #[test]
+    fn page_eq_unresolved() {
+        let wikipedia = Wikipedia::<MockClient>::default();
+        let nyc = wikipedia.page_from_title("NYC".to_owned());
+        assert!(nyc == nyc);
+        assert!(nyc == wikipedia.page_from_title("NYC".to_owned()));
+        assert!(nyc != wikipedia.page_from_title("New York City".to_owned()));
+        assert!(nyc != wikipedia.page_from_pageid("645042".to_owned()));
+        let other = Wikipedia::<MockClient> {
+            language: "es".to_owned(),
+            ..Wikipedia::default()
+        };
+        assert!(nyc != other.page_from_title("NYC".to_owned()));
+        assert!(wikipedia.client.arguments.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn resolve_missing() {
+        let wikipedia = Wikipedia::<MockClient>::default();
+        wikipedia.client.response.lock().unwrap().push("{\"query\":{\"pages\":{\"-1\":{\"ns\":0,\"title\":\"Foo bar baz\",\"missing\":\"\"}}}}".to_owned());
+        let (page, resolution) = wikipedia.page_from_title("Foo bar baz".to_owned()).resolve().unwrap();
+        assert_eq!(resolution.pageid, None);
+        assert!(page == wikipedia.page_from_title("Foo bar baz".to_owned()));
+    }
+
+    #[test]
+    fn redirect_chain() {
+        let wikipedia = Wikipedia::<MockClient>::default();
+        wikipedia.client.response.lock().unwrap().push("{\"query\":{\"redirects\":[{\"from\":\"B\",\"to\":\"C\"},{\"from\":\"A\",\"to\":\"B\"}]}}".to_owned());
+        wikipedia.client.response.lock().unwrap().push("{\"query\":{\"pages\":{\"3\":{\"extract\":\"hello\"}}}}".to_owned());
+        let page = wikipedia.page_from_title("A".to_owned());
+        assert_eq!(page.get_summary().unwrap(), "hello".to_owned());
+        assert_eq!(wikipedia.client.arguments.lock().unwrap()[1].last().unwrap(),
+                &("titles".to_owned(), "C".to_owned()));
+    }
+
+    #[test]
+    fn section_tree() {
+        let wikipedia = Wikipedia::<MockClient>::default();
+        wikipedia.client.response.lock().unwrap().push("{\"parse\":{\"title\":\"World\",\"pageid\":123,\"sections\":[{\"toclevel\":1,\"level\":\"2\",\"line\":\"History\",\"number\":\"1\",\"index\":\"1\",\"fromtitle\":\"World\",\"byteoffset\":100,\"anchor\":\"History\"},{\"toclevel\":2,\"level\":\"3\",\"line\":\"Ancient\",\"number\":\"1.1\",\"index\":\"2\",\"fromtitle\":\"World\",\"byteoffset\":200,\"anchor\":\"Ancient\"},{\"toclevel\":3,\"level\":\"4\",\"line\":\"Rome\",\"number\":\"1.1.1\",\"index\":\"3\",\"fromtitle\":\"World\",\"byteoffset\":300,\"anchor\":\"Rome\"},{\"toclevel\":2,\"level\":\"3\",\"line\":\"Modern\",\"number\":\"1.2\",\"index\":\"4\",\"fromtitle\":\"World\",\"byteoffset\":400,\"anchor\":\"Modern\"},{\"toclevel\":1,\"level\":\"2\",\"line\":\"See also\",\"number\":\"2\",\"index\":\"T-1\",\"fromtitle\":\"Template:Foo\",\"byteoffset\":null,\"anchor\":\"See_also\"}]}}".to_owned());
+        let page = wikipedia.page_from_pageid("123".to_owned());
+        let tree = page.get_section_tree().unwrap();
+        assert_eq!(tree.len(), 2);
+        assert_eq!(tree[0].title, "History".to_owned());
+        assert_eq!(tree[0].children.len(), 2);
+        assert_eq!(tree[0].children[0].children, vec![super::Section {
+            title: "Rome".to_owned(),
+            level: 4,
+            number: "1.1.1".to_owned(),
+            index: "3".to_owned(),
+            anchor: "Rome".to_owned(),
+            byte_offset: Some(300),
+            children: Vec::new(),
+        }]);
+        assert_eq!(tree[0].children[1].title, "Modern".to_owned());
+        assert_eq!(tree[1].index, "T-1".to_owned());
+        assert_eq!(tree[1].anchor, "See_also".to_owned());
+        assert_eq!(tree[1].byte_offset, None);
+    }
+
+    #[test]
+    fn section_content() {
+        let content = "Intro.\n\n\n== History ==\nOld.\n\n=== Rome ===\nRoman.\n\n== Notes ==\nFirst.\n\n== Notes ==\nSecond.";
+        assert_eq!(super::section_content(content, "History"),
+                Some("Old.\n\n=== Rome ===\nRoman.".to_owned()));
+        assert_eq!(super::section_content(content, "Rome"), Some("Roman.".to_owned()));
+        assert_eq!(super::section_content(content, "Notes"), Some("First.".to_owned()));
+        assert_eq!(super::section_content(content, "Foo"), None);
+    }
+
+    #[test]
+    fn non_ascii_headings() {
+        assert_eq!(super::parse_heading("== Bär =="), Some((2, "Bär".to_owned())));
+        assert_eq!(super::parse_heading("==Bär"), None);
+        assert_eq!(super::parse_heading("=é"), None);
+        assert_eq!(super::parse_heading("é="), None);
+        assert_eq!(super::section_content("Intro.\n==Bär\n== Ödön ==\nText.", "Ödön"),
+                Some("Text.".to_owned()));
+        assert_eq!(super::document::Document::parse("==Bär").paragraphs, vec!["==Bär".to_owned()]);
+        super::wikitext::parse("=é");
+    }
+
+    #[test]
+    fn section_text() {
+        let wikipedia = Wikipedia::<MockClient>::default();
+        wikipedia.client.response.lock().unwrap().push("{\"parse\":{\"title\":\"World\",\"pageid\":123,\"wikitext\":{\"*\":\"== Notes ==\\nSecond [[Earth|planet]].\\n\\n\\n''Last''.\"}}}".to_owned());
+        wikipedia.client.response.lock().unwrap().push("{\"parse\":{\"title\":\"World\",\"pageid\":123,\"wikitext\":{\"*\":\"== History ==\\nOld.\\n\\n=== Rome ===\\nRoman.\"}}}".to_owned());
+        wikipedia.client.response.lock().unwrap().push("{\"error\":{\"code\":\"nosuchsection\",\"info\":\"There is no section 9.\"}}".to_owned());
+        let page = wikipedia.page_from_pageid("123".to_owned());
+        assert_eq!(page.get_section_text("4", true).unwrap(), Some("Second planet.\n\nLast.".to_owned()));
+        assert_eq!(page.get_section_text("1", false).unwrap(), Some("Old.".to_owned()));
+        assert_eq!(page.get_section_text("9", true).unwrap(), None);
+        let arguments = wikipedia.client.arguments.lock().unwrap();
+        assert_eq!(arguments.len(), 3);
+        assert!(arguments[0].contains(&("section".to_owned(), "4".to_owned())));
+    }
+
+    #[test]
+    fn section_wikitext() {
+        let wikipedia = Wikipedia::<MockClient>::default();
+        wikipedia.client.response.lock().unwrap().push("{\"parse\":{\"title\":\"World\",\"pageid\":123,\"wikitext\":{\"*\":\"== History ==\\nOld.\\n\\n=== Rome ===\\nRoman.\"}}}".to_owned());
+        wikipedia.client.response.lock().unwrap().push("{\"parse\":{\"title\":\"World\",\"pageid\":123,\"wikitext\":{\"*\":\"== History ==\\nOld.\\n\\n=== Rome ===\\nRoman.\"}}}".to_owned());
+        let page = wikipedia.page_from_pageid("123".to_owned());
+        assert_eq!(page.get_section_wikitext("1", true).unwrap(),
+                "== History ==\nOld.\n\n=== Rome ===\nRoman.".to_owned());
+        assert_eq!(page.get_section_wikitext("1", false).unwrap(), "== History ==\nOld.".to_owned());
+        assert_eq!(wikipedia.client.arguments.lock().unwrap()[0], vec![
+            ("prop".to_owned(), "wikitext".to_owned()),
+            ("section".to_owned(), "1".to_owned()),
+            ("format".to_owned(), "json".to_owned()),
+            ("action".to_owned(), "parse".to_owned()),
+            ("pageid".to_owned(), "123".to_owned()),
+        ]);
+    }
+
+    #[test]
+    fn wikitext() {
+        let wikipedia = Wikipedia::<MockClient>::default();
+        wikipedia.client.response.lock().unwrap().push("{\"query\":{\"pages\":{\"1\":{\"pageid\":1,\"title\":\"World\",\"revisions\":[{\"slots\":{\"main\":{\"contentmodel\":\"wikitext\",\"contentformat\":\"text/x-wiki\",\"*\":\"'''World''' is [[Earth]].\"}}}]}}}}".to_owned());
+        let page = wikipedia.page_from_title("World".to_owned());
+        assert_eq!(page.get_wikitext().unwrap(), "'''World''' is [[Earth]].".to_owned());
+        assert_eq!(*wikipedia.client.arguments.lock().unwrap(), vec![vec![
+            ("prop".to_owned(), "revisions".to_owned()),
+            ("rvprop".to_owned(), "content".to_owned()),
+            ("rvslots".to_owned(), "main".to_owned()),
+            ("redirects".to_owned(), "".to_owned()),
+            ("format".to_owned(), "json".to_owned()),
+            ("action".to_owned(), "query".to_owned()),
+            ("titles".to_owned(), "World".to_owned()),
+        ]]);
+    }
+
+    #[test]
+    fn get_revisions() {
+        let wikipedia = Wikipedia::<MockClient>::default();
+        wikipedia.client.response.lock().unwrap().push("{\"continue\":{\"rvcontinue\":\"20160101000000|3\",\"continue\":\"||\"},\"query\":{\"pages\":{\"1\":{\"pageid\":1,\"title\":\"World\",\"revisions\":[{\"revid\":2,\"parentid\":1,\"minor\":\"\",\"user\":\"Alice\",\"timestamp\":\"2015-01-01T00:00:00Z\",\"size\":120,\"sha1\":\"abc\",\"comment\":\"typo\",\"tags\":[\"mobile edit\"]}]}}}}".to_owned());
+        wikipedia.client.response.lock().unwrap().push("{\"query\":{\"pages\":{\"1\":{\"pageid\":1,\"title\":\"World\",\"revisions\":[{\"revid\":3,\"parentid\":2,\"user\":\"Bob\",\"timestamp\":\"2016-01-01T00:00:00Z\",\"size\":100,\"sha1\":\"def\",\"comment\":\"\",\"tags\":[]}]}}}}".to_owned());
+        let page = wikipedia.page_from_title("World".to_owned());
+        let revisions = page.get_revisions(&iter::RevisionOptions {
+            direction: iter::Direction::Newer,
+            start: Some("2015-01-01T00:00:00Z".to_owned()),
+            ..iter::RevisionOptions::default()
+        }).unwrap().collect::<Vec<_>>();
+        assert_eq!(revisions.len(), 2);
+        assert_eq!(revisions[0], iter::Revision {
+            revid: 2,
+            parentid: 1,
+            timestamp: "2015-01-01T00:00:00Z".to_owned(),
+            user: "Alice".to_owned(),
+            comment: "typo".to_owned(),
+            size: 120,
+            minor: true,
+            tags: vec!["mobile edit".to_owned()],
+            sha1: "abc".to_owned(),
+        });
+        assert!(!revisions[1].minor);
+        let arguments = wikipedia.client.arguments.lock().unwrap();
+        assert_eq!(arguments[1], vec![
+            ("prop".to_owned(), "revisions".to_owned()),
+            ("rvprop".to_owned(), "ids|timestamp|user|comment|size|flags|tags|sha1".to_owned()),
+            ("rvlimit".to_owned(), "max".to_owned()),
+            ("rvdir".to_owned(), "newer".to_owned()),
+            ("rvstart".to_owned(), "2015-01-01T00:00:00Z".to_owned()),
+            ("format".to_owned(), "json".to_owned()),
+            ("action".to_owned(), "query".to_owned()),
+            ("titles".to_owned(), "World".to_owned()),
+            ("continue".to_owned(), "||".to_owned()),
+            ("rvcontinue".to_owned(), "20160101000000|3".to_owned()),
+        ]);
+    }
+
+    #[test]
+    fn get_langlinks() {
+        let wikipedia = Wikipedia::new(Arc::new(MockClient::default()));
+        wikipedia.client.response.lock().unwrap().push("{\"continue\":{\"llcontinue\":\"1|fr\",\"continue\":\"||\"},\"query\":{\"pages\":{\"1\":{\"pageid\":1,\"title\":\"World\",\"langlinks\":[{\"lang\":\"es\",\"url\":\"https://es.wikipedia.org/wiki/Mundo\",\"autonym\":\"espa\\u00f1ol\",\"*\":\"Mundo\"}]}}}}".to_owned());
+        wikipedia.client.response.lock().unwrap().push("{\"query\":{\"pages\":{\"1\":{\"pageid\":1,\"title\":\"World\",\"langlinks\":[{\"lang\":\"fr\",\"url\":\"https://fr.wikipedia.org/wiki/Monde\",\"autonym\":\"fran\\u00e7ais\",\"*\":\"Monde\"}]}}}}".to_owned());
+        wikipedia.client.response.lock().unwrap().push("{\"query\":{\"pages\":{\"2\":{\"pageid\":2,\"title\":\"Mundo\"}}}}".to_owned());
+        let page = wikipedia.page_from_title("World".to_owned());
+        let links = page.get_langlinks().unwrap().collect::<Vec<_>>();
+        assert_eq!(links, vec![
+            iter::LangLink {
+                lang: "es".to_owned(),
+                title: "Mundo".to_owned(),
+                url: "https://es.wikipedia.org/wiki/Mundo".to_owned(),
+                autonym: "español".to_owned(),
+            },
+            iter::LangLink {
+                lang: "fr".to_owned(),
+                title: "Monde".to_owned(),
+                url: "https://fr.wikipedia.org/wiki/Monde".to_owned(),
+                autonym: "français".to_owned(),
+            },
+        ]);
+        assert_eq!(wikipedia.client.arguments.lock().unwrap()[0][..3].to_vec(), vec![
+            ("prop".to_owned(), "langlinks".to_owned()),
+            ("llprop".to_owned(), "url|autonym".to_owned()),
+            ("lllimit".to_owned(), "max".to_owned()),
+        ]);
+
+        let es = wikipedia.with_language(&links[0].lang);
+        assert_eq!(links[0].page(&es).get_pageid().unwrap(), "2".to_owned());
+        assert_eq!(*wikipedia.client.url.lock().unwrap(), vec![
+            "https://en.wikipedia.org/w/api.php".to_owned(),
+            "https://en.wikipedia.org/w/api.php".to_owned(),
+            "https://es.wikipedia.org/w/api.php".to_owned(),
+        ]);
+        assert_eq!(wikipedia.client.user_agent, es.client.user_agent);
+    }
+
+    #[test]
+    fn for_language() {
+        let wikipedia = Wikipedia::new(Arc::new(MockClient::default()));
+        for _ in 0..2 {
+            wikipedia.client.response.lock().unwrap().push("{\"query\":{\"languages\":[{\"code\":\"en\",\"*\":\"English\"},{\"code\":\"es\",\"*\":\"espa\\u00f1ol\"}]}}".to_owned());
+        }
+        wikipedia.client.response.lock().unwrap().push("{\"query\":{\"pages\":{\"2\":{\"pageid\":2,\"title\":\"Mundo\"}}}}".to_owned());
+        match wikipedia.for_language("xx") {
+            Err(Error::InvalidParameter(ref p)) if p == "language" => (),
+            r => panic!("unexpected result {:?}", r.map(|w| w.language)),
+        }
+        let es = wikipedia.for_language("es").unwrap();
+        assert_eq!(es.language, "es".to_owned());
+        assert_eq!(es.page_from_title("Mundo".to_owned()).get_pageid().unwrap(), "2".to_owned());
+        assert_eq!(wikipedia.client.url.lock().unwrap()[2], "https://es.wikipedia.org/w/api.php".to_owned());
+        assert_eq!(Arc::strong_count(&wikipedia.client), 2);
+    }
+
+    #[test]
+    fn for_language_in() {
+        let wikipedia = Wikipedia::new(Arc::new(MockClient::default()));
+        let languages = vec![("en".to_owned(), "English".to_owned()), ("es".to_owned(), "español".to_owned())];
+        assert_eq!(wikipedia.for_language_in("es", &languages).unwrap().base_url(),
+                "https://es.wikipedia.org/w/api.php".to_owned());
+        assert!(wikipedia.for_language_in("xx", &languages).is_err());
+        assert!(wikipedia.client.arguments.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn get_backlinks() {
+        let wikipedia = Wikipedia::<MockClient>::default();
+        wikipedia.client.response.lock().unwrap().push("{\"continue\":{\"blcontinue\":\"0|30\",\"continue\":\"-||\"},\"query\":{\"backlinks\":[{\"pageid\":10,\"ns\":0,\"title\":\"Earth\"},{\"pageid\":20,\"ns\":0,\"title\":\"The World\",\"redirect\":\"\",\"redirlinks\":[{\"pageid\":21,\"ns\":0,\"title\":\"Globe\"}]}]}}".to_owned());
+        wikipedia.client.response.lock().unwrap().push("{\"query\":{\"backlinks\":[{\"pageid\":30,\"ns\":0,\"title\":\"Planet\"}]}}".to_owned());
+        let page = wikipedia.page_from_title("World".to_owned());
+        let options = iter::BacklinkOptions {
+            namespaces: vec![0, 14],
+            follow_redirects: true,
+            ..iter::BacklinkOptions::default()
+        };
+        let backlink = |pageid, title: &str, redirect, via: Option<&str>| iter::Backlink {
+            pageid,
+            ns: 0,
+            title: title.to_owned(),
+            redirect,
+            via: via.map(|v| v.to_owned()),
+        };
+        assert_eq!(page.get_backlinks(&options).unwrap().collect::<Vec<_>>(), vec![
+            backlink(10, "Earth", false, None),
+            backlink(20, "The World", true, None),
+            backlink(21, "Globe", false, Some("The World")),
+            backlink(30, "Planet", false, None),
+        ]);
+        let arguments = wikipedia.client.arguments.lock().unwrap();
+        assert_eq!(arguments[1], vec![
+            ("list".to_owned(), "backlinks".to_owned()),
+            ("bllimit".to_owned(), "max".to_owned()),
+            ("blfilterredir".to_owned(), "all".to_owned()),
+            ("blnamespace".to_owned(), "0|14".to_owned()),
+            ("blredirect".to_owned(), "".to_owned()),
+            ("bltitle".to_owned(), "World".to_owned()),
+            ("format".to_owned(), "json".to_owned()),
+            ("action".to_owned(), "query".to_owned()),
+            ("blcontinue".to_owned(), "0|30".to_owned()),
+            ("continue".to_owned(), "-||".to_owned()),
+        ]);
+    }
+
+    #[test]
+    fn category_members() {
+        let wikipedia = Wikipedia::<MockClient>::default();
+        wikipedia.client.response.lock().unwrap().push("{\"continue\":{\"cmcontinue\":\"page|41|2\",\"continue\":\"-||\"},\"query\":{\"categorymembers\":[{\"pageid\":1,\"ns\":14,\"title\":\"Category:Football clubs in Buenos Aires\",\"type\":\"subcat\",\"timestamp\":\"2015-01-01T00:00:00Z\"}]}}".to_owned());
+        wikipedia.client.response.lock().unwrap().push("{\"query\":{\"categorymembers\":[{\"pageid\":2,\"ns\":0,\"title\":\"Club Atlético Banfield\",\"type\":\"page\",\"timestamp\":\"2016-01-01T00:00:00Z\"}]}}".to_owned());
+        let members = wikipedia.category_members("Argentine football clubs", &iter::CategoryMemberOptions {
+            types: vec![iter::MemberType::Page, iter::MemberType::Subcat],
+            sort: iter::CategorySort::Timestamp,
+            descending: true,
+            start: Some("2017-01-01T00:00:00Z".to_owned()),
+            ..iter::CategoryMemberOptions::default()
+        }).unwrap().collect::<Vec<_>>();
+        assert_eq!(members, vec![
+            iter::CategoryMember {
+                pageid: 1,
+                ns: 14,
+                title: "Category:Football clubs in Buenos Aires".to_owned(),
+                member_type: iter::MemberType::Subcat,
+                timestamp: "2015-01-01T00:00:00Z".to_owned(),
+            },
+            iter::CategoryMember {
+                pageid: 2,
+                ns: 0,
+                title: "Club Atlético Banfield".to_owned(),
+                member_type: iter::MemberType::Page,
+                timestamp: "2016-01-01T00:00:00Z".to_owned(),
+            },
+        ]);
+        let arguments = wikipedia.client.arguments.lock().unwrap();
+        assert_eq!(arguments[0], vec![
+            ("list".to_owned(), "categorymembers".to_owned()),
+            ("cmprop".to_owned(), "ids|title|type|timestamp".to_owned()),
+            ("cmlimit".to_owned(), "max".to_owned()),
+            ("cmsort".to_owned(), "timestamp".to_owned()),
+            ("cmdir".to_owned(), "desc".to_owned()),
+            ("cmtype".to_owned(), "page|subcat".to_owned()),
+            ("cmstart".to_owned(), "2017-01-01T00:00:00Z".to_owned()),
+            ("cmtitle".to_owned(), "Category:Argentine football clubs".to_owned()),
+            ("format".to_owned(), "json".to_owned()),
+            ("action".to_owned(), "query".to_owned()),
+            ("continue".to_owned(), "".to_owned()),
+        ]);
+        assert_eq!(arguments[1][arguments[1].len() - 2..].to_vec(), vec![
+            ("cmcontinue".to_owned(), "page|41|2".to_owned()),
+            ("continue".to_owned(), "-||".to_owned()),
+        ]);
+    }
+
+    #[test]
+    fn category_tree() {
+        let wikipedia = Wikipedia::<MockClient>::default();
+        let member = |title: &str, member_type: &str| format!(
+            "{{\"pageid\":1,\"ns\":0,\"title\":\"{}\",\"type\":\"{}\",\"timestamp\":\"\"}}", title, member_type);
+        let response = |members: Vec<String>| format!(
+            "{{\"query\":{{\"categorymembers\":[{}]}}}}", members.join(","));
+        wikipedia.client.response.lock().unwrap().push(response(vec![
+            member("Category:Clubs", "subcat"),
+            member("Banfield", "page"),
+        ]));
+        wikipedia.client.response.lock().unwrap().push(response(vec![
+            member("Category:Football", "subcat"),
+            member("Banfield", "page"),
+            member("Category:Stadiums", "subcat"),
+            member("Boca Juniors", "page"),
+        ]));
+        let tree = wikipedia.category_tree("Football", 1)
+            .map(|r| r.map(|(m, path)| (m.title, path)))
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(tree, vec![
+            ("Banfield".to_owned(), vec!["Category:Football".to_owned()]),
+            ("Boca Juniors".to_owned(), vec!["Category:Football".to_owned(), "Category:Clubs".to_owned()]),
+        ]);
+        let arguments = wikipedia.client.arguments.lock().unwrap();
+        assert_eq!(arguments.len(), 2);
+        assert!(arguments[1].contains(&("cmtitle".to_owned(), "Category:Clubs".to_owned())));
+    }
 }
@@ -0,0 +1,190 @@
+//! Tables of an article laid out as a grid of plain text cells, see
+//! `Page::get_tables`.
+use std::cmp;
+use std::collections::BTreeMap;
+
+use serde_json;
+use serde_json::Value;
+
+use super::wikitext;
+use super::wikitext::Node;
+
+/// Largest `rowspan` or `colspan` honored, so that broken markup cannot
+/// create huge tables.
+const MAX_SPAN: usize = 1000;
+
+/// A table with `rowspan` and `colspan` resolved: a spanning cell is
+/// repeated in every position it covers, so all rows have the same length.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Table {
+    pub caption: Option<String>,
+    /// The `class` attribute, for example "wikitable sortable".
+    pub class: String,
+    /// Number of rows at the start of `rows` made only of header cells.
+    pub header_rows: usize,
+    pub rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    /// Builds a `Table` from a parsed wikitext table. Cells are rendered
+    /// with `wikitext::plain_text`.
+    pub fn from_wikitext(table: &wikitext::Table) -> Table {
+        let mut grid: Vec<Vec<Option<String>>> = Vec::new();
+        let row_count = table.rows.len();
+        for (r, row) in table.rows.iter().enumerate() {
+            let mut c = 0;
+            for cell in &row.cells {
+                while grid.get(r).and_then(|x| x.get(c)).map(|x| x.is_some()).unwrap_or(false) {
+                    c += 1;
+                }
+                let rowspan = match span(cell.attribute("rowspan")) {
+                    0 => row_count - r,
+                    n => cmp::min(n, row_count - r),
+                };
+                let colspan = cmp::max(span(cell.attribute("colspan")), 1);
+                let text = cell_text(&cell.content);
+                for i in r..r + rowspan {
+                    while grid.len() <= i {
+                        grid.push(Vec::new());
+                    }
+                    let row = &mut grid[i];
+                    while row.len() < c + colspan {
+                        row.push(None);
+                    }
+                    for cell in &mut row[c..c + colspan] {
+                        *cell = Some(text.clone());
+                    }
+                }
+                c += colspan;
+            }
+        }
+        let width = grid.iter().map(|r| r.len()).max().unwrap_or(0);
+        let rows = grid
+            .into_iter()
+            .map(|r| {
+                let mut r: Vec<String> = r.into_iter().map(|c| c.unwrap_or_default()).collect();
+                r.resize(width, String::new());
+                r
+            })
+            .collect();
+        Table {
+            caption: table.caption.as_ref().map(|c| cell_text(c)),
+            class: table.attributes
+                .iter()
+                .find(|a| a.0 == "class")
+                .map(|a| a.1.clone())
+                .unwrap_or_default(),
+            header_rows: table.rows
+                .iter()
+                .take_while(|r| !r.cells.is_empty() && r.cells.iter().all(|c| c.header))
+                .count(),
+            rows,
+        }
+    }
+
+    /// Returns the header rows.
+    pub fn headers(&self) -> &[Vec<String>] {
+        &self.rows[..cmp::min(self.header_rows, self.rows.len())]
+    }
+
+    /// Returns the rows after the headers.
+    pub fn body(&self) -> &[Vec<String>] {
+        &self.rows[cmp::min(self.header_rows, self.rows.len())..]
+    }
+
+    /// Renders all rows, headers included, as CSV.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::new();
+        for row in &self.rows {
+            let fields: Vec<String> = row.iter().map(|f| {
+                if f.contains([',', '"', '\n', '\r']) {
+                    format!("\"{}\"", f.replace('"', "\"\""))
+                } else {
+                    f.clone()
+                }
+            }).collect();
+            csv.push_str(&fields.join(","));
+            csv.push_str("\r\n");
+        }
+        csv
+    }
+
+    /// Renders the table as a JSON object with the keys `caption`, `headers`
+    /// and `rows`, the latter two being arrays of arrays of strings.
+    pub fn to_json(&self) -> String {
+        let rows = |rows: &[Vec<String>]| Value::Array(rows
+            .iter()
+            .map(|r| Value::Array(r.iter().map(|c| Value::String(c.clone())).collect()))
+            .collect());
+        let mut obj = BTreeMap::new();
+        obj.insert("caption".to_owned(), match self.caption {
+            Some(ref c) => Value::String(c.clone()),
+            None => Value::Null,
+        });
+        obj.insert("headers".to_owned(), rows(self.headers()));
+        obj.insert("rows".to_owned(), rows(self.body()));
+        serde_json::to_string(&Value::Object(obj)).unwrap_or_default()
+    }
+}
+
+/// Finds the tables in `nodes`, including tables nested in table cells.
+pub fn tables(nodes: &[Node]) -> Vec<Table> {
+    let mut tables = Vec::new();
+    for node in nodes {
+        if let Node::Table(ref t) = *node {
+            tables.push(Table::from_wikitext(t));
+            for cell in t.rows.iter().flat_map(|r| r.cells.iter()) {
+                tables.extend(self::tables(&cell.content));
+            }
+        }
+    }
+    tables
+}
+
+fn span(value: Option<&str>) -> usize {
+    value
+        .and_then(|v| v.trim().parse::<usize>().ok())
+        .map(|v| cmp::min(v, MAX_SPAN))
+        .unwrap_or(1)
+}
+
+fn cell_text(content: &[Node]) -> String {
+    wikitext::plain_text(content).split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Table, tables};
+    use super::super::wikitext;
+
+    const TABLE: &str = "{| class=\"wikitable\"\n|+ [[Primera División]] results\n|-\n! rowspan=2 | Season !! colspan=\"2\" | Result\n|-\n! Position !! Points\n|-\n| 2001 || 1st || 40\n|-\n| 2002 || colspan=2 | Cancelled, \"void\"\n|}";
+
+    fn table() -> Table {
+        tables(&wikitext::parse(TABLE)).into_iter().next().unwrap()
+    }
+
+    #[test]
+    fn spans() {
+        assert_eq!(table(), Table {
+            caption: Some("Primera División results".to_owned()),
+            class: "wikitable".to_owned(),
+            header_rows: 2,
+            rows: vec![
+                vec!["Season".to_owned(), "Result".to_owned(), "Result".to_owned()],
+                vec!["Season".to_owned(), "Position".to_owned(), "Points".to_owned()],
+                vec!["2001".to_owned(), "1st".to_owned(), "40".to_owned()],
+                vec!["2002".to_owned(), "Cancelled, \"void\"".to_owned(), "Cancelled, \"void\"".to_owned()],
+            ],
+        });
+    }
+
+    #[test]
+    fn csv() {
+        assert_eq!(table().to_csv(), "Season,Result,Result\r\nSeason,Position,Points\r\n2001,1st,40\r\n2002,\"Cancelled, \"\"void\"\"\",\"Cancelled, \"\"void\"\"\"\r\n".to_owned());
+    }
+
+    #[test]
+    fn json() {
+        assert_eq!(table().to_json(), "{\"caption\":\"Primera División results\",\"headers\":[[\"Season\",\"Result\",\"Result\"],[\"Season\",\"Position\",\"Points\"]],\"rows\":[[\"2001\",\"1st\",\"40\"],[\"2002\",\"Cancelled, \\\"void\\\"\",\"Cancelled, \\\"void\\\"\"]]}".to_owned());
+    }
+}
@@ -0,0 +1,844 @@
+//! A parser for wikitext, the markup articles are written in, see
+//! `Page::get_wikitext`.
+//!
+//! It covers headings, links, templates, tables, references, lists, bold and
+//! italic text and comments. Parsing never fails: unknown markup is kept as
+//! text and unclosed markup runs to the end of the input.
+use super::parse_heading;
+
+/// A piece of parsed wikitext.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Node {
+    /// Plain text, including line breaks.
+    Text(String),
+    /// `== title ==`
+    Heading { level: u8, children: Vec<Node> },
+    /// `[[target|text]]`, `text` is empty if there is no `|`.
+    Link { target: String, text: Vec<Node> },
+    /// `[url text]`
+    ExternalLink { url: String, text: Vec<Node> },
+    /// `{{name|value|key=value}}`
+    Template(Template),
+    /// `{| ... |}`
+    Table(Table),
+    /// `<ref name="x">content</ref>` or `<ref name="x" />`
+    Ref { name: Option<String>, children: Vec<Node> },
+    /// A line starting with `*`, `#`, `:` or `;`. `marker` keeps all of
+    /// them, so "**" is a bullet nested in another one.
+    ListItem { marker: String, children: Vec<Node> },
+    /// `'''text'''`
+    Bold(Vec<Node>),
+    /// `''text''`
+    Italic(Vec<Node>),
+    /// `<!-- text -->`
+    Comment(String),
+}
+
+/// A template call like `{{Infobox city|name=Paris}}`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    pub name: String,
+    pub params: Vec<Parameter>,
+}
+
+/// A parameter of a `Template`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Parameter {
+    /// Name of the parameter, `None` for positional parameters.
+    pub name: Option<String>,
+    /// Parsed value, without surrounding whitespace.
+    pub value: Vec<Node>,
+    /// The value's wikitext, without surrounding whitespace.
+    pub raw: String,
+}
+
+/// A table like `{| class="wikitable" ... |}`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Table {
+    pub attributes: Vec<(String, String)>,
+    pub caption: Option<Vec<Node>>,
+    pub rows: Vec<TableRow>,
+}
+
+/// A row of a `Table`, started by `|-`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableRow {
+    pub attributes: Vec<(String, String)>,
+    pub cells: Vec<TableCell>,
+}
+
+/// A cell of a `TableRow`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableCell {
+    /// The cell starts with `!` instead of `|`.
+    pub header: bool,
+    /// Attributes such as `rowspan` and `colspan`, with lowercase names.
+    pub attributes: Vec<(String, String)>,
+    pub content: Vec<Node>,
+}
+
+impl Template {
+    /// Returns the parameter named `key`. Positional parameters are named by
+    /// their position among them, starting at "1".
+    pub fn get(&self, key: &str) -> Option<&Parameter> {
+        let mut position = 0;
+        for param in &self.params {
+            let matches = match param.name {
+                Some(ref name) => name == key,
+                None => {
+                    position += 1;
+                    format!("{}", position) == key
+                },
+            };
+            if matches {
+                return Some(param);
+            }
+        }
+        None
+    }
+}
+
+/// An infobox, the summary table at the top of many articles, see
+/// `Page::get_infobox`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Infobox {
+    /// Name of the template, for example "Infobox settlement".
+    pub name: String,
+    /// Non-empty parameters, in the order they appear.
+    pub fields: Vec<(String, InfoboxValue)>,
+}
+
+/// The value of an `Infobox` field.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InfoboxValue {
+    /// The value's wikitext.
+    pub raw: String,
+    /// The value as plain text, see `plain_text`. Lines, such as the items
+    /// of a list, are joined with ", ".
+    pub text: String,
+}
+
+impl Infobox {
+    /// Returns the value of the field `key`.
+    pub fn get(&self, key: &str) -> Option<&InfoboxValue> {
+        self.fields.iter().find(|f| f.0 == key).map(|f| &f.1)
+    }
+}
+
+impl TableCell {
+    /// Returns the value of the attribute `name`.
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes.iter().find(|a| a.0 == name).map(|a| &*a.1)
+    }
+}
+
+/// Parses `text` into a list of nodes.
+pub fn parse(text: &str) -> Vec<Node> {
+    Parser { text, pos: 0 }.parse_nodes(&[])
+}
+
+/// Finds the first infobox template in `nodes`, looking into the parameters
+/// of other templates as well.
+pub fn infobox(nodes: &[Node]) -> Option<Infobox> {
+    for node in nodes {
+        let template = match *node {
+            Node::Template(ref t) => t,
+            _ => continue,
+        };
+        if template_name(&template.name).starts_with("infobox") {
+            let mut position = 0;
+            let fields = template.params.iter().filter_map(|p| {
+                let key = match p.name {
+                    Some(ref name) => name.clone(),
+                    None => {
+                        position += 1;
+                        format!("{}", position)
+                    },
+                };
+                if p.raw.is_empty() {
+                    return None;
+                }
+                let text = plain_text(&p.value);
+                let text = text
+                    .lines()
+                    .map(|l| l.split_whitespace().collect::<Vec<_>>().join(" "))
+                    .filter(|l| !l.is_empty())
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                Some((key, InfoboxValue { raw: p.raw.clone(), text }))
+            }).collect();
+            return Some(Infobox { name: template.name.clone(), fields });
+        }
+        for p in &template.params {
+            if let Some(i) = infobox(&p.value) {
+                return Some(i);
+            }
+        }
+    }
+    None
+}
+
+/// Renders `nodes` as plain text. Links are replaced by their text,
+/// formatting and HTML tags are dropped, and tables, references, comments
+/// and links to files and categories are removed. Common templates such as
+/// `{{convert}}`, `{{birth date}}` or `{{ubl}}` are rendered, while others
+/// are removed.
+pub fn plain_text(nodes: &[Node]) -> String {
+    let mut text = String::new();
+    for node in nodes {
+        match *node {
+            Node::Text(ref t) => text.push_str(&strip_tags(t)),
+            Node::Heading { ref children, .. } |
+                    Node::ListItem { ref children, .. } |
+                    Node::Bold(ref children) |
+                    Node::Italic(ref children) => text.push_str(&plain_text(children)),
+            Node::Link { ref target, text: ref t } => {
+                let prefix = target.split(':').next().unwrap_or("").trim().to_lowercase();
+                if target.contains(':') && !target.starts_with(':') &&
+                        (prefix == "file" || prefix == "image" || prefix == "category") {
+                    continue;
+                }
+                if t.is_empty() {
+                    text.push_str(target.trim_start_matches(':'));
+                } else {
+                    text.push_str(&plain_text(t));
+                }
+            },
+            Node::ExternalLink { text: ref t, .. } => text.push_str(&plain_text(t)),
+            Node::Template(ref t) => text.push_str(&template_text(t)),
+            Node::Table(_) | Node::Ref { .. } | Node::Comment(_) => (),
+        }
+    }
+    text
+}
+
+/// Lowercases a template name and replaces underscores with spaces.
+fn template_name(name: &str) -> String {
+    name.trim().replace('_', " ").to_lowercase()
+}
+
+/// Renders the templates `plain_text` knows about.
+fn template_text(template: &Template) -> String {
+    let args: Vec<String> = template.params
+        .iter()
+        .filter(|p| p.name.is_none())
+        .map(|p| plain_text(&p.value).trim().to_owned())
+        .collect();
+    let arg = |i: usize| args.get(i).cloned().unwrap_or(String::new());
+    match &*template_name(&template.name) {
+        "convert" | "cvt" => convert_text(&args),
+        "birth date" | "birth date and age" | "bda" | "death date" | "death date and age" |
+            "dda" | "start date" | "start date and age" | "end date" | "film date" =>
+            date_text(&args),
+        "ubl" | "unbulleted list" | "hlist" | "flatlist" | "plainlist" | "plain list" =>
+            args.join("\n"),
+        "nowrap" | "nobr" | "small" | "big" | "sic" | "abbr" | "flag" | "flagcountry" |
+            "flag country" | "marriage" => arg(0),
+        "lang" => arg(1),
+        _ => String::new(),
+    }
+}
+
+/// Renders `{{convert|value|unit}}` or `{{convert|value|to|value|unit}}`
+/// without converting the value.
+fn convert_text(args: &[String]) -> String {
+    let ranges = ["to", "-", "\u{2013}", "and", "or", "by", "x", "\u{d7}", "+/-", "\u{b1}"];
+    if args.len() >= 4 && ranges.contains(&&*args[1]) {
+        format!("{} {} {} {}", args[0], args[1], args[2], args[3])
+    } else if args.len() >= 2 {
+        format!("{} {}", args[0], args[1])
+    } else {
+        args.first().cloned().unwrap_or(String::new())
+    }
+}
+
+/// Renders the year, month and day arguments of a date template, like
+/// "10 December 1815".
+fn date_text(args: &[String]) -> String {
+    const MONTHS: [&str; 12] = ["January", "February", "March", "April", "May", "June",
+        "July", "August", "September", "October", "November", "December"];
+    let number = |i: usize| args.get(i).and_then(|x| x.parse::<u32>().ok());
+    let month = number(1).and_then(|m| if (1..=12).contains(&m) { Some(MONTHS[m as usize - 1]) } else { None });
+    match (number(0), month, number(2)) {
+        (Some(y), Some(m), Some(d)) => format!("{} {} {}", d, m, y),
+        (Some(y), Some(m), None) => format!("{} {}", m, y),
+        (Some(y), None, _) => format!("{}", y),
+        _ => args.first().cloned().unwrap_or(String::new()),
+    }
+}
+
+/// Removes HTML tags, replacing `<br>` with a line break, and decodes the
+/// most common entities.
+fn strip_tags(text: &str) -> String {
+    let mut out = String::new();
+    let mut rest = text;
+    while let Some(i) = rest.find(['<', '&']) {
+        out.push_str(&rest[..i]);
+        rest = &rest[i..];
+        if rest.starts_with('<') {
+            let name: String = rest[1..]
+                .trim_start_matches('/')
+                .chars()
+                .take_while(|c| c.is_ascii_alphanumeric())
+                .collect();
+            match rest.find('>') {
+                Some(end) if !name.is_empty() && name.starts_with(|c: char| c.is_ascii_alphabetic()) => {
+                    if name.eq_ignore_ascii_case("br") {
+                        out.push('\n');
+                    }
+                    rest = &rest[end + 1..];
+                    continue;
+                },
+                _ => (),
+            }
+        } else {
+            let entities = [("&nbsp;", " "), ("&ndash;", "\u{2013}"), ("&mdash;", "\u{2014}"),
+                ("&amp;", "&"), ("&lt;", "<"), ("&gt;", ">"), ("&quot;", "\"")];
+            if let Some(&(entity, value)) = entities.iter().find(|e| rest.starts_with(e.0)) {
+                out.push_str(value);
+                rest = &rest[entity.len()..];
+                continue;
+            }
+        }
+        out.push_str(&rest[..1]);
+        rest = &rest[1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Parses HTML-like attributes such as `colspan="2" style=x`.
+pub fn parse_attributes(text: &str) -> Vec<(String, String)> {
+    let mut attributes = Vec::new();
+    let mut rest = text.trim_start();
+    while !rest.is_empty() {
+        let name_end = rest.find(|c: char| c.is_whitespace() || c == '=').unwrap_or(rest.len());
+        let name = rest[..name_end].to_lowercase();
+        rest = rest[name_end..].trim_start();
+        let mut value = String::new();
+        if rest.starts_with('=') {
+            rest = rest[1..].trim_start();
+            let quote = rest.chars().next().filter(|&c| c == '"' || c == '\'');
+            let (v, r) = match quote {
+                Some(q) => match rest[1..].find(q) {
+                    Some(i) => (&rest[1..i + 1], &rest[i + 2..]),
+                    None => (&rest[1..], ""),
+                },
+                None => {
+                    let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+                    (&rest[..end], &rest[end..])
+                },
+            };
+            value = v.to_owned();
+            rest = r;
+        }
+        if !name.is_empty() {
+            attributes.push((name, value));
+        }
+        rest = rest.trim_start();
+    }
+    attributes
+}
+
+/// Where a list of nodes ends.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Stop {
+    /// `|` or `}}` in a template.
+    Param,
+    /// `]]` in a link.
+    LinkText,
+    /// `]` in an external link.
+    ExternalLinkText,
+    /// `</ref>`
+    Ref,
+    /// End of the line in a heading or list item.
+    Line,
+    Bold,
+    Italic,
+    /// A new cell or row in a table, or the `|` after the cell's
+    /// attributes. The flag is set for header cells, which can also be
+    /// separated by `!!`.
+    CellAttributes(bool),
+    /// A new cell or row in a table.
+    Cell(bool),
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.text[self.pos..]
+    }
+
+    fn at(&self, s: &str) -> bool {
+        self.rest().starts_with(s)
+    }
+
+    fn at_line_start(&self) -> bool {
+        self.pos == 0 || self.text.as_bytes()[self.pos - 1] == b'\n'
+    }
+
+    /// Returns the rest of the current line, moving to its end.
+    fn take_line(&mut self) -> &'a str {
+        let rest = self.rest();
+        let end = rest.find('\n').unwrap_or(rest.len());
+        self.pos += end;
+        &rest[..end]
+    }
+
+    fn matches(&self, stop: Stop) -> bool {
+        let rest = self.rest();
+        match stop {
+            Stop::Param => rest.starts_with('|') || rest.starts_with("}}"),
+            Stop::LinkText => rest.starts_with("]]"),
+            Stop::ExternalLinkText => rest.starts_with(']') || rest.starts_with('\n'),
+            Stop::Ref => rest.get(..6).map(|x| x.eq_ignore_ascii_case("</ref>")).unwrap_or(false),
+            Stop::Line => rest.starts_with('\n'),
+            Stop::Bold => rest.starts_with("'''") || rest.starts_with('\n'),
+            Stop::Italic => rest.starts_with('\n') || (rest.starts_with("''") &&
+                    (!rest.starts_with("'''") || rest.starts_with("'''''"))),
+            Stop::CellAttributes(header) => rest.starts_with('|') || self.matches(Stop::Cell(header)),
+            Stop::Cell(header) => rest.starts_with("||") || (header && rest.starts_with("!!")) ||
+                (rest.starts_with('\n') && rest[1..]
+                    .trim_start_matches([' ', '\t'])
+                    .starts_with(['|', '!'])),
+        }
+    }
+
+    fn parse_nodes(&mut self, stops: &[Stop]) -> Vec<Node> {
+        let mut nodes = Vec::new();
+        let mut text = String::new();
+        while self.pos < self.text.len() {
+            if stops.iter().any(|&s| self.matches(s)) {
+                break;
+            }
+            if self.at("<nowiki") {
+                if let Some(t) = self.parse_nowiki() {
+                    text.push_str(t);
+                    continue;
+                }
+            }
+            match self.parse_node(stops) {
+                Some(node) => {
+                    if !text.is_empty() {
+                        nodes.push(Node::Text(text.clone()));
+                        text.clear();
+                    }
+                    nodes.push(node);
+                },
+                None => {
+                    let c = self.rest().chars().next().unwrap();
+                    text.push(c);
+                    self.pos += c.len_utf8();
+                },
+            }
+        }
+        if !text.is_empty() {
+            nodes.push(Node::Text(text));
+        }
+        nodes
+    }
+
+    fn parse_node(&mut self, stops: &[Stop]) -> Option<Node> {
+        if self.at("<!--") {
+            self.pos += 4;
+            let end = self.rest().find("-->").unwrap_or(self.rest().len());
+            let comment = self.rest()[..end].to_owned();
+            self.pos = (self.pos + end + 3).min(self.text.len());
+            return Some(Node::Comment(comment));
+        }
+        if self.at_line_start() {
+            if self.at("{|") {
+                return Some(self.parse_table());
+            }
+            let line = &self.rest()[..self.rest().find('\n').unwrap_or(self.rest().len())];
+            if let Some((level, title)) = parse_heading(line) {
+                self.pos += line.len();
+                return Some(Node::Heading { level, children: parse(&title) });
+            }
+            if self.rest().starts_with(|c| "*#:;".contains(c)) {
+                let marker_len = self.rest().find(|c| !"*#:;".contains(c)).unwrap_or(self.rest().len());
+                let marker = self.rest()[..marker_len].to_owned();
+                self.pos += marker_len;
+                let children = trim(self.parse_nodes(&with(stops, Stop::Line)));
+                return Some(Node::ListItem { marker, children });
+            }
+        }
+        if self.at("{{{") {
+            // Template arguments only have a meaning inside templates.
+            let end = self.rest().find("}}}").map(|i| i + 3).unwrap_or(self.rest().len());
+            let raw = self.rest()[..end].to_owned();
+            self.pos += end;
+            return Some(Node::Text(raw));
+        }
+        if self.at("{{") {
+            return Some(self.parse_template());
+        }
+        if self.at("[[") {
+            return Some(self.parse_link());
+        }
+        if self.at("[") && is_url(&self.rest()[1..]) {
+            return Some(self.parse_external_link());
+        }
+        if self.at("'''") {
+            self.pos += 3;
+            let children = self.parse_nodes(&with(stops, Stop::Bold));
+            if self.at("'''") {
+                self.pos += 3;
+            }
+            return Some(Node::Bold(children));
+        }
+        if self.at("''") {
+            self.pos += 2;
+            let children = self.parse_nodes(&with(stops, Stop::Italic));
+            if self.at("''") {
+                self.pos += 2;
+            }
+            return Some(Node::Italic(children));
+        }
+        if self.rest().get(..4).map(|x| x.eq_ignore_ascii_case("<ref")).unwrap_or(false) &&
+                self.rest()[4..].starts_with(|c: char| c.is_whitespace() || c == '>' || c == '/') {
+            return self.parse_ref();
+        }
+        None
+    }
+
+    /// Returns the content of `<nowiki>...</nowiki>`, or an empty string for
+    /// `<nowiki/>`.
+    fn parse_nowiki(&mut self) -> Option<&'a str> {
+        let rest = self.rest();
+        let tag_end = match rest.find('>') {
+            Some(i) => i + 1,
+            None => return None,
+        };
+        let tag = &rest[..tag_end];
+        if tag.ends_with("/>") {
+            self.pos += tag_end;
+            return Some("");
+        }
+        if tag != "<nowiki>" {
+            return None;
+        }
+        let end = rest[tag_end..].find("</nowiki>").map(|i| tag_end + i).unwrap_or(rest.len());
+        self.pos = (self.pos + end + "</nowiki>".len()).min(self.text.len());
+        Some(&rest[tag_end..end])
+    }
+
+    fn parse_template(&mut self) -> Node {
+        self.pos += 2;
+        let start = self.pos;
+        self.parse_nodes(&[Stop::Param]);
+        let name = self.text[start..self.pos].trim().to_owned();
+        let mut params = Vec::new();
+        while self.at("|") {
+            self.pos += 1;
+            let start = self.pos;
+            let value = self.parse_nodes(&[Stop::Param]);
+            params.push(parameter(&self.text[start..self.pos], value));
+        }
+        if self.at("}}") {
+            self.pos += 2;
+        }
+        Node::Template(Template { name, params })
+    }
+
+    fn parse_link(&mut self) -> Node {
+        self.pos += 2;
+        let end = self.rest().find(['|', ']']).unwrap_or(self.rest().len());
+        let target = self.rest()[..end].trim().to_owned();
+        self.pos += end;
+        let text = if self.at("|") {
+            self.pos += 1;
+            trim(self.parse_nodes(&[Stop::LinkText]))
+        } else {
+            Vec::new()
+        };
+        if self.at("]]") {
+            self.pos += 2;
+        }
+        Node::Link { target, text }
+    }
+
+    fn parse_external_link(&mut self) -> Node {
+        self.pos += 1;
+        let end = self.rest()
+            .find(|c: char| c.is_whitespace() || c == ']')
+            .unwrap_or(self.rest().len());
+        let url = self.rest()[..end].to_owned();
+        self.pos += end;
+        while self.at(" ") || self.at("\t") {
+            self.pos += 1;
+        }
+        let text = trim(self.parse_nodes(&[Stop::ExternalLinkText]));
+        if self.at("]") {
+            self.pos += 1;
+        }
+        Node::ExternalLink { url, text }
+    }
+
+    fn parse_ref(&mut self) -> Option<Node> {
+        let tag_end = match self.rest().find('>') {
+            Some(i) => i + 1,
+            None => return None,
+        };
+        let tag = &self.rest()[..tag_end];
+        let self_closing = tag.ends_with("/>");
+        let attributes = parse_attributes(tag[4..tag.len() - 1].trim_end_matches('/'));
+        let name = attributes.into_iter().find(|a| a.0 == "name").map(|a| a.1);
+        self.pos += tag_end;
+        let children = if self_closing {
+            Vec::new()
+        } else {
+            let children = trim(self.parse_nodes(&[Stop::Ref]));
+            if self.matches(Stop::Ref) {
+                self.pos += "</ref>".len();
+            }
+            children
+        };
+        Some(Node::Ref { name, children })
+    }
+
+    fn parse_table(&mut self) -> Node {
+        self.pos += 2;
+        let mut table = Table {
+            attributes: parse_attributes(self.take_line()),
+            caption: None,
+            rows: Vec::new(),
+        };
+        while self.pos < self.text.len() {
+            if self.at("\n") {
+                self.pos += 1;
+            }
+            while self.at(" ") || self.at("\t") {
+                self.pos += 1;
+            }
+            if self.at("|}") {
+                self.pos += 2;
+                break;
+            }
+            if self.at("|+") {
+                self.pos += 2;
+                table.caption = Some(trim(self.parse_nodes(&[Stop::Cell(false)])));
+            } else if self.at("|-") {
+                self.pos += 2;
+                let attributes = parse_attributes(self.take_line());
+                match table.rows.last_mut() {
+                    Some(ref mut row) if row.cells.is_empty() => {
+                        row.attributes = attributes;
+                        continue;
+                    },
+                    _ => (),
+                }
+                table.rows.push(TableRow { attributes, cells: Vec::new() });
+            } else if self.at("|") || self.at("!") {
+                let header = self.at("!");
+                self.pos += 1;
+                loop {
+                    let cell = self.parse_cell(header);
+                    if table.rows.is_empty() {
+                        table.rows.push(TableRow { attributes: Vec::new(), cells: Vec::new() });
+                    }
+                    table.rows.last_mut().unwrap().cells.push(cell);
+                    if self.at("||") || (header && self.at("!!")) {
+                        self.pos += 2;
+                    } else {
+                        break;
+                    }
+                }
+            } else {
+                self.take_line();
+            }
+        }
+        if table.rows.last().map(|r| r.cells.is_empty()).unwrap_or(false) {
+            table.rows.pop();
+        }
+        Node::Table(table)
+    }
+
+    fn parse_cell(&mut self, header: bool) -> TableCell {
+        let start = self.pos;
+        let mut content = self.parse_nodes(&[Stop::CellAttributes(header)]);
+        let mut attributes = Vec::new();
+        if self.at("|") && !self.at("||") {
+            let prefix = &self.text[start..self.pos];
+            self.pos += 1;
+            let rest = self.parse_nodes(&[Stop::Cell(header)]);
+            if content.iter().all(|n| matches!(*n, Node::Text(_))) {
+                attributes = parse_attributes(prefix);
+                content = rest;
+            } else {
+                content.push(Node::Text("|".to_owned()));
+                content.extend(rest);
+            }
+        }
+        TableCell { header, attributes, content: trim(content) }
+    }
+}
+
+fn with(stops: &[Stop], stop: Stop) -> Vec<Stop> {
+    let mut stops = stops.to_vec();
+    stops.push(stop);
+    stops
+}
+
+fn is_url(text: &str) -> bool {
+    ["http://", "https://", "ftp://", "//", "mailto:"]
+        .iter()
+        .any(|scheme| text.len() >= scheme.len() &&
+             text.get(..scheme.len()).map(|x| x.eq_ignore_ascii_case(scheme)).unwrap_or(false))
+}
+
+/// Removes the whitespace around a list of nodes.
+fn trim(mut nodes: Vec<Node>) -> Vec<Node> {
+    if let Some(&mut Node::Text(ref mut t)) = nodes.first_mut() {
+        *t = t.trim_start().to_owned();
+    }
+    if let Some(&mut Node::Text(ref mut t)) = nodes.last_mut() {
+        *t = t.trim_end().to_owned();
+    }
+    nodes.retain(|n| *n != Node::Text(String::new()));
+    nodes
+}
+
+/// Builds a template parameter, named if its text starts with `key=`.
+fn parameter(raw: &str, mut value: Vec<Node>) -> Parameter {
+    let split = match value.first() {
+        Some(Node::Text(t)) => t.find('=').map(|i| (t[..i].trim().to_owned(), t[i + 1..].to_owned())),
+        _ => None,
+    };
+    match split {
+        Some((name, rest)) => {
+            value[0] = Node::Text(rest);
+            let raw = &raw[raw.find('=').unwrap() + 1..];
+            Parameter { name: Some(name), value: trim(value), raw: raw.trim().to_owned() }
+        },
+        None => Parameter { name: None, value: trim(value), raw: raw.trim().to_owned() },
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{InfoboxValue, Node, Parameter, Template, infobox, parse, parse_attributes, plain_text};
+
+    fn text(s: &str) -> Node {
+        Node::Text(s.to_owned())
+    }
+
+    #[test]
+    fn links_and_formatting() {
+        assert_eq!(parse("A '''[[Cat|big cat]]''' and ''[https://example.org site]''."), vec![
+            text("A "),
+            Node::Bold(vec![Node::Link { target: "Cat".to_owned(), text: vec![text("big cat")] }]),
+            text(" and "),
+            Node::Italic(vec![Node::ExternalLink {
+                url: "https://example.org".to_owned(),
+                text: vec![text("site")],
+            }]),
+            text("."),
+        ]);
+    }
+
+    #[test]
+    fn templates() {
+        let nodes = parse("{{Infobox person\n| name = [[Ada Lovelace]]\n| birth_date = {{birth date|1815|12|10}}\n| London\n}}");
+        let template = match nodes[0] {
+            Node::Template(ref t) => t,
+            ref n => panic!("unexpected {:?}", n),
+        };
+        assert_eq!(template.name, "Infobox person".to_owned());
+        assert_eq!(template.get("name"), Some(&Parameter {
+            name: Some("name".to_owned()),
+            value: vec![Node::Link { target: "Ada Lovelace".to_owned(), text: vec![] }],
+            raw: "[[Ada Lovelace]]".to_owned(),
+        }));
+        assert_eq!(template.get("birth_date").unwrap().value, vec![Node::Template(Template {
+            name: "birth date".to_owned(),
+            params: vec![
+                Parameter { name: None, value: vec![text("1815")], raw: "1815".to_owned() },
+                Parameter { name: None, value: vec![text("12")], raw: "12".to_owned() },
+                Parameter { name: None, value: vec![text("10")], raw: "10".to_owned() },
+            ],
+        })]);
+        assert_eq!(template.get("1").unwrap().raw, "London".to_owned());
+    }
+
+    #[test]
+    fn headings_lists_and_refs() {
+        let nodes = parse("== History ==\n* One<ref name=\"a\">[[Source]]</ref>\n** Two<ref name=a /><!-- c -->");
+        assert_eq!(nodes, vec![
+            Node::Heading { level: 2, children: vec![text("History")] },
+            text("\n"),
+            Node::ListItem { marker: "*".to_owned(), children: vec![
+                text("One"),
+                Node::Ref {
+                    name: Some("a".to_owned()),
+                    children: vec![Node::Link { target: "Source".to_owned(), text: vec![] }],
+                },
+            ] },
+            text("\n"),
+            Node::ListItem { marker: "**".to_owned(), children: vec![
+                text("Two"),
+                Node::Ref { name: Some("a".to_owned()), children: vec![] },
+                Node::Comment(" c ".to_owned()),
+            ] },
+        ]);
+    }
+
+    #[test]
+    fn tables() {
+        let nodes = parse("{| class=\"wikitable\"\n|+ Results\n|-\n! Year !! Team\n|-\n| rowspan=\"2\" | 2001 || [[River Plate|River]]\n|-\n| Boca\n|}");
+        let table = match nodes[0] {
+            Node::Table(ref t) => t,
+            ref n => panic!("unexpected {:?}", n),
+        };
+        assert_eq!(table.attributes, vec![("class".to_owned(), "wikitable".to_owned())]);
+        assert_eq!(table.caption, Some(vec![text("Results")]));
+        assert_eq!(table.rows.len(), 3);
+        assert!(table.rows[0].cells.iter().all(|c| c.header));
+        assert_eq!(table.rows[0].cells[1].content, vec![text("Team")]);
+        assert_eq!(table.rows[1].cells[0].attribute("rowspan"), Some("2"));
+        assert_eq!(table.rows[1].cells[0].content, vec![text("2001")]);
+        assert_eq!(plain_text(&table.rows[1].cells[1].content), "River".to_owned());
+        assert_eq!(table.rows[2].cells[0].content, vec![text("Boca")]);
+        assert_eq!(nodes.len(), 1);
+    }
+
+    #[test]
+    fn attributes() {
+        assert_eq!(parse_attributes(" colspan=2 Style='color: red' hidden"), vec![
+            ("colspan".to_owned(), "2".to_owned()),
+            ("style".to_owned(), "color: red".to_owned()),
+            ("hidden".to_owned(), "".to_owned()),
+        ]);
+    }
+
+    #[test]
+    fn plain() {
+        let nodes = parse("'''Paris''' is the [[capital city|capital]] of [[France]].{{efn|x}}[[File:Paris.jpg|thumb|A view]]<ref>x</ref> <nowiki>[[raw]]</nowiki>");
+        assert_eq!(plain_text(&nodes), "Paris is the capital of France. [[raw]]".to_owned());
+    }
+
+    #[test]
+    fn templates_text() {
+        let nodes = parse("{{convert|1,000|km|mi}}, {{convert|1|to|2|m}}, {{birth date and age|1815|12|10|df=y}}, {{lang|fr|Paris}}, {{cite web|url=x}}A&nbsp;B<br />C<small>D</small>");
+        assert_eq!(plain_text(&nodes), "1,000 km, 1 to 2 m, 10 December 1815, Paris, A B\nCD".to_owned());
+    }
+
+    #[test]
+    fn infoboxes() {
+        let nodes = parse("{{Short description|Capital of France}}\n{{Infobox_settlement\n| name = Paris\n| image =\n| population = {{convert|2,102,650|}}<ref>x</ref>\n| leader = {{ubl|[[Anne Hidalgo]]|Someone}}\n}}\n'''Paris''' is a city.");
+        let infobox = infobox(&nodes).unwrap();
+        assert_eq!(infobox.name, "Infobox_settlement".to_owned());
+        assert_eq!(infobox.fields.iter().map(|f| &*f.0).collect::<Vec<_>>(),
+                vec!["name", "population", "leader"]);
+        assert_eq!(infobox.get("population"), Some(&InfoboxValue {
+            raw: "{{convert|2,102,650|}}<ref>x</ref>".to_owned(),
+            text: "2,102,650".to_owned(),
+        }));
+        assert_eq!(infobox.get("leader").unwrap().text, "Anne Hidalgo, Someone".to_owned());
+        assert_eq!(super::infobox(&parse("No infobox")), None);
+    }
+}
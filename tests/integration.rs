@@ -1,10 +1,13 @@
 #[cfg(feature="http-client")] extern crate hyper;
 extern crate wikipedia;
 
-#[cfg(feature = "http-client")]
+// These tests send requests to en.wikipedia.org, run them with
+// `cargo test --features live-tests`.
+#[cfg(feature = "live-tests")]
 mod tests {
     use wikipedia::Wikipedia;
     use wikipedia::http;
+    use wikipedia::http::RecordingClient;
     use std::collections::HashSet;
 
     fn w() -> Wikipedia<http::hyper::Client> {
@@ -237,4 +240,15 @@ mod tests {
         assert!(languages.contains(&("en".to_owned(), "English".to_owned())));
         assert!(languages.contains(&("es".to_owned(), "español".to_owned())));
     }
+
+    // Rewrites the fixture replayed by tests/replay.rs, run it with
+    // `cargo test --features live-tests -- --ignored record_triviality`.
+    #[test]
+    #[ignore]
+    fn record_triviality() {
+        let client = RecordingClient::new(http::hyper::Client::default(), "tests/fixtures/triviality.json");
+        let wikipedia = Wikipedia::new(client);
+        wikipedia.search("bikeshedding").unwrap();
+        wikipedia.page_from_title("Bikeshedding".to_owned()).get_summary().unwrap();
+    }
 }
@@ -0,0 +1,28 @@
+extern crate wikipedia;
+
+use wikipedia::Wikipedia;
+use wikipedia::http::ReplayClient;
+
+fn w() -> Wikipedia<ReplayClient> {
+    Wikipedia::new(ReplayClient::open("tests/fixtures/triviality.json").unwrap())
+}
+
+#[test]
+fn search() {
+    let wikipedia = w();
+    let results = wikipedia.search("bikeshedding").unwrap();
+    assert!(results.contains(&"Law of triviality".to_owned()));
+}
+
+#[test]
+fn redirect_summary() {
+    let wikipedia = w();
+    let page = wikipedia.page_from_title("Bikeshedding".to_owned());
+    assert!(page.get_summary().unwrap().contains("trivial issues"));
+}
+
+#[test]
+fn unrecorded() {
+    let wikipedia = w();
+    assert!(wikipedia.search("hello world").is_err());
+}